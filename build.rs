@@ -4,6 +4,11 @@ use clap_mangen::Man;
 use std::io::Error;
 use std::path::Path;
 
+// cli.rs starts commands through the shared helpers
+#[allow(dead_code)]
+#[path = "src/helpers.rs"]
+mod helpers;
+
 include!("src/cli.rs");
 
 fn completions(outdir: &Path) -> Result<(), Error> {
@@ -25,11 +30,12 @@ fn manpage(outdir: &Path) -> Result<(), Error> {
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("cargo:rerun-if-changed=src/cli.rs");
+    println!("cargo:rerun-if-changed=src/helpers.rs");
 
     let outdir = std::env::var("OUT_DIR").unwrap();
     let dest = Path::new(&outdir).ancestors().nth(3).unwrap();
-    std::fs::create_dir_all(&dest.join("completions"))?;
-    std::fs::create_dir_all(&dest.join("man"))?;
+    std::fs::create_dir_all(dest.join("completions"))?;
+    std::fs::create_dir_all(dest.join("man"))?;
     completions(&dest.join("completions"))?;
     manpage(&dest.join("man"))?;
 
@@ -2,6 +2,7 @@ use clap::Parser;
 use serde::Serialize;
 use std::collections::HashMap;
 use std::fs;
+use std::io::Read;
 use std::path::PathBuf;
 
 // alignment enumerator
@@ -38,12 +39,32 @@ impl std::fmt::Display for Locker {
     }
 }
 
+// directory change enumerator
+#[derive(clap::ValueEnum, Clone, Copy, Serialize, PartialEq)]
+pub enum OnChange {
+    Switch,    // Show newly added images
+    Rescan,    // Only update the known images
+    Randomize, // Pick another random image
+}
+
+// hook failure enumerator
+#[derive(clap::ValueEnum, Clone, Copy, Serialize, PartialEq)]
+pub enum HookFailure {
+    Abort,  // Stop the run with an error
+    Warn,   // Report the failure and continue
+    Ignore, // Continue silently
+}
+
 // backend enumerator
 #[derive(clap::ValueEnum, Clone, Serialize, PartialEq)]
 pub enum Backend {
     Wpaperd,
     Swaybg,
     Hyprpaper,
+    Native,
+    Swww,
+    Sway,
+    Custom,
 }
 
 impl std::fmt::Display for Backend {
@@ -58,25 +79,210 @@ impl std::fmt::Display for Backend {
             Self::Hyprpaper => {
                 write!(f, "hyprpaper")
             }
+            Self::Native => {
+                write!(f, "native")
+            }
+            Self::Swww => {
+                write!(f, "swww")
+            }
+            Self::Sway => {
+                write!(f, "sway")
+            }
+            Self::Custom => {
+                write!(f, "custom")
+            }
         }
     }
 }
 
+// wpaperd mode enumerator
+#[derive(clap::ValueEnum, Clone, Copy, Serialize, PartialEq)]
+pub enum WpaperdMode {
+    Center,
+    Fit,
+    FitBorderColor,
+    Stretch,
+    Tile,
+}
+
+impl std::fmt::Display for WpaperdMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Center => write!(f, "center"),
+            Self::Fit => write!(f, "fit"),
+            Self::FitBorderColor => write!(f, "fit-border-color"),
+            Self::Stretch => write!(f, "stretch"),
+            Self::Tile => write!(f, "tile"),
+        }
+    }
+}
+
+// swww transition type enumerator
+#[derive(clap::ValueEnum, Clone, Copy, Serialize, PartialEq)]
+pub enum SwwwTransition {
+    None,
+    Simple,
+    Fade,
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Wipe,
+    Wave,
+    Grow,
+    Center,
+    Any,
+    Outer,
+    Random,
+}
+
+impl std::fmt::Display for SwwwTransition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Simple => write!(f, "simple"),
+            Self::Fade => write!(f, "fade"),
+            Self::Left => write!(f, "left"),
+            Self::Right => write!(f, "right"),
+            Self::Top => write!(f, "top"),
+            Self::Bottom => write!(f, "bottom"),
+            Self::Wipe => write!(f, "wipe"),
+            Self::Wave => write!(f, "wave"),
+            Self::Grow => write!(f, "grow"),
+            Self::Center => write!(f, "center"),
+            Self::Any => write!(f, "any"),
+            Self::Outer => write!(f, "outer"),
+            Self::Random => write!(f, "random"),
+        }
+    }
+}
+
+// hyprpaper fit mode enumerator, values are the ones of its protocol
+#[derive(clap::ValueEnum, Clone, Copy, Serialize, PartialEq)]
+pub enum HyprpaperFit {
+    Stretch = 0,
+    Cover = 1,
+    Contain = 2,
+    Tile = 3,
+}
+
+// schedule slot enumerator
+#[derive(Clone, Copy, Serialize, PartialEq)]
+pub enum Slot {
+    Morning,
+    Day,
+    Evening,
+    Night,
+}
+
+impl std::fmt::Display for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Morning => {
+                write!(f, "morning")
+            }
+            Self::Day => {
+                write!(f, "day")
+            }
+            Self::Evening => {
+                write!(f, "evening")
+            }
+            Self::Night => {
+                write!(f, "night")
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Slot {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input {
+            "morning" => Ok(Self::Morning),
+            "day" => Ok(Self::Day),
+            "evening" => Ok(Self::Evening),
+            "night" => Ok(Self::Night),
+            _ => Err(format!("\"{}\": invalid schedule slot", input)),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct ScheduleEntry {
+    pub slot: Slot,
+    pub input_path: PathBuf,
+    pub raw_input_path: PathBuf,
+}
+
 #[derive(clap::Args)]
-#[group(required = true, multiple = false)]
+#[group(required = true, multiple = true)]
 pub struct InitGroup {
-    /// Image file or directory path
-    #[arg(short, long)]
+    /// Image file, directory or playlist path, or "-" to read from stdin
+    #[arg(short, long, conflicts_with_all = ["info", "schedule", "image_cmd"])]
     image: Option<String>,
 
+    /// Command that prints an image path or writes image data to stdout
+    #[arg(long, conflicts_with_all = ["info", "schedule"])]
+    image_cmd: Option<String>,
+
+    /// List of images or directories per time of day [format: "<SLOT>:<PATH>"]
+    #[arg(long, num_args = 1..)]
+    schedule: Option<Vec<String>>,
+
     /// Show detectable information
     #[arg(long)]
     info: bool,
 }
 
+// daemon control commands
+#[derive(clap::Subcommand, Clone, PartialEq)]
+pub enum Control {
+    /// Show the next image
+    Next,
+    /// Show the previous image
+    Previous,
+    /// Show a specific image, directory or playlist
+    Set {
+        /// Image file, directory or playlist path
+        path: String,
+    },
+    /// Reload the configuration and resplit
+    Reload,
+    /// Print the current daemon status
+    Status {
+        /// Keep printing the status as single lines whenever it changes
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Pause the image rotation
+    Pause,
+    /// Resume the image rotation
+    Resume,
+    /// Resplit for the current monitors
+    Resplit {
+        /// Skip all image cache checks
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Stop the daemon
+    Quit,
+}
+
+#[derive(clap::Subcommand)]
+pub enum Mode {
+    /// Control a running daemon
+    Ctl {
+        #[command(subcommand)]
+        command: Control,
+    },
+    /// Print a suggested systemd user unit
+    Unit,
+}
+
 /// Multi-Monitor Wallpaper Utility
 #[derive(Parser)]
-#[command(version, about, long_about = None, help_template = "\
+#[command(version, about, long_about = None, subcommand_negates_reqs = true, args_conflicts_with_subcommands = true, args_override_self = true, help_template = "\
 {name} {version} - {about}
 
 {usage-heading}
@@ -85,9 +291,16 @@ pub struct InitGroup {
 {all-args}
 ")]
 struct Args {
+    #[command(subcommand)]
+    mode: Option<Mode>,
+
     #[clap(flatten)]
     init_group: InitGroup,
 
+    /// Config file path [default: $XDG_CONFIG_HOME/rwpspread/config.toml]
+    #[arg(short, long)]
+    config: Option<String>,
+
     /// Output directory path
     #[arg(short, long)]
     output: Option<String>,
@@ -96,10 +309,51 @@ struct Args {
     #[arg(short, long, value_enum)]
     align: Option<Alignment>,
 
-    /// Wallpaper setter backend
-    #[arg(short, long, value_enum)]
+    /// Wallpaper setter backend, native draws the wallpaper itself in daemon mode
+    #[arg(
+        short,
+        long,
+        value_enum,
+        requires_ifs([("native", "daemon"), ("custom", "custom_cmd")])
+    )]
     backend: Option<Backend>,
 
+    /// How wpaperd fits the images to the outputs
+    #[arg(long, value_enum, default_value_t = WpaperdMode::Center)]
+    wpaperd_mode: WpaperdMode,
+
+    /// How hyprpaper fits the images to the outputs
+    #[arg(long, value_enum, default_value_t = HyprpaperFit::Cover)]
+    hyprpaper_fit: HyprpaperFit,
+
+    /// Version of the hyprpaper protocol to bind, hyprpaper has to offer it
+    #[arg(long, default_value_t = 1)]
+    hyprpaper_version: u32,
+
+    /// Command template of the custom backend, run per {output} and {path} or once for {all}
+    #[arg(long)]
+    custom_cmd: Option<String>,
+
+    /// Stop the running custom command before starting it again
+    #[arg(long, requires = "custom_cmd")]
+    custom_kill: bool,
+
+    /// Do not wait for the custom command, it keeps running to show the wallpaper
+    #[arg(long, requires = "custom_cmd")]
+    custom_long_running: bool,
+
+    /// Transition type when switching images with swww
+    #[arg(long, value_enum, requires = "backend")]
+    transition: Option<SwwwTransition>,
+
+    /// Transition duration in seconds when switching images with swww
+    #[arg(long, value_parser = Config::to_duration, requires = "backend")]
+    transition_duration: Option<f32>,
+
+    /// Transition frame rate when switching images with swww
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), requires = "backend")]
+    transition_fps: Option<u32>,
+
     /// Lockscreen implementation to generate for
     #[arg(short, long, value_enum)]
     locker: Option<Locker>,
@@ -120,25 +374,81 @@ struct Args {
     #[arg(short, long)]
     daemon: bool,
 
+    /// Run once, even if the config file enables daemon mode
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "daemon",
+            "on_hotplug",
+            "on_image_change",
+            "on_error",
+            "on_backend_restart",
+            "watch",
+            "on_change",
+        ]
+    )]
+    no_daemon: bool,
+
     /// Generate a color palette from input image
     #[arg(short, long)]
     palette: bool,
 
-    /// Script to execute before splitting
-    #[arg(long)]
-    pre: Option<String>,
+    /// Command to execute before splitting, a single value runs in a shell
+    #[arg(long, num_args = 1..)]
+    pre: Option<Vec<String>>,
 
-    /// Script to execute after splitting
-    #[arg(long)]
-    post: Option<String>,
+    /// Command to execute after splitting, a single value runs in a shell
+    #[arg(long, num_args = 1..)]
+    post: Option<Vec<String>>,
+
+    /// Seconds a hook may run before it is killed, 0 to wait forever
+    #[arg(long, default_value_t = 30)]
+    hook_timeout: u64,
+
+    /// What to do when a hook fails or times out
+    #[arg(long, value_enum, default_value_t = HookFailure::Warn)]
+    hook_failure: HookFailure,
+
+    /// Command to execute when outputs were added or removed
+    #[arg(long, num_args = 1.., requires = "daemon")]
+    on_hotplug: Option<Vec<String>>,
+
+    /// Command to execute when the shown image changed
+    #[arg(long, num_args = 1.., requires = "daemon")]
+    on_image_change: Option<Vec<String>>,
+
+    /// Command to execute when a resplit failed
+    #[arg(long, num_args = 1.., requires = "daemon")]
+    on_error: Option<Vec<String>>,
+
+    /// Command to execute when a crashed backend was restarted
+    #[arg(long, num_args = 1.., requires = "daemon")]
+    on_backend_restart: Option<Vec<String>>,
 
     /// Watch for wallpaper source changes and resplit on changes
     #[arg(short, long, requires = "daemon")]
     watch: bool,
 
+    /// What to do when images are added to a watched directory
+    #[arg(long, value_enum, default_value_t = OnChange::Randomize, requires = "watch")]
+    on_change: OnChange,
+
     /// Force resplit, skips all image cache checks
     #[arg(short, long)]
     force_resplit: bool,
+
+    /// List of schedule slot start times [format: "<SLOT>:<HH:MM>"]
+    #[clap(long, value_delimiter = ' ', num_args = 1.., requires = "schedule")]
+    times: Option<Vec<String>>,
+
+    /// Derive schedule slot start times from sunrise and sunset [format: "<LAT>:<LON>"]
+    #[arg(
+        long,
+        allow_hyphen_values = true,
+        requires = "schedule",
+        conflicts_with = "times"
+    )]
+    location: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -147,6 +457,20 @@ pub struct Config {
     pub raw_input_path: PathBuf,
     pub output_path: Option<String>,
     pub backend: Option<Backend>,
+    #[serde(skip)]
+    pub transition: Option<SwwwTransition>,
+    #[serde(skip)]
+    pub transition_duration: Option<f32>,
+    #[serde(skip)]
+    pub transition_fps: Option<u32>,
+    pub wpaperd_mode: WpaperdMode,
+    #[serde(skip)]
+    pub hyprpaper_fit: HyprpaperFit,
+    #[serde(skip)]
+    pub hyprpaper_version: u32,
+    pub custom_cmd: Option<String>,
+    pub custom_kill: bool,
+    pub custom_long_running: bool,
     pub locker: Option<Locker>,
     pub bezel: Option<u32>,
     pub diagonals: HashMap<String, u32>,
@@ -156,21 +480,275 @@ pub struct Config {
     pub force_resplit: bool,
     pub info: bool,
     pub align: Option<Alignment>,
-    pub pre_path: Option<String>,
-    pub post_path: Option<String>,
+    pub pre_command: Option<Vec<String>>,
+    pub post_command: Option<Vec<String>>,
+    pub hook_timeout: Option<u64>,
+    pub hook_failure: HookFailure,
+    #[serde(skip)]
+    pub on_hotplug: Option<Vec<String>>,
+    #[serde(skip)]
+    pub on_image_change: Option<Vec<String>>,
+    #[serde(skip)]
+    pub on_error: Option<Vec<String>>,
+    #[serde(skip)]
+    pub on_backend_restart: Option<Vec<String>>,
     pub watch: bool,
+    pub on_change: OnChange,
+    pub schedule: Vec<ScheduleEntry>,
+    pub schedule_times: Vec<(Slot, u32)>,
+    pub location: Option<(f64, f64)>,
+    #[serde(skip)]
+    pub input_bytes: Option<Vec<u8>>,
+    #[serde(skip)]
+    pub config_path: Option<PathBuf>,
     version: String,
 }
 
 impl Config {
     /// Generate and return a new Config based on cli input
     pub fn new() -> Result<Option<Self>, String> {
-        // handle args
-        let mut args = Args::parse();
+        // exit on invalid args, or when help or version was requested
+        let (args, config_path) = Config::parse_args()?;
+        Config::from_args(args.unwrap_or_else(|err| err.exit()), config_path)
+    }
+    /// Reload and return the Config, rejecting invalid args instead of exiting
+    pub fn reload() -> Result<Option<Self>, String> {
+        let (args, config_path) = Config::parse_args()?;
+        let args = args.map_err(|err| {
+            // only keep the actual message without usage hints
+            let message = err.to_string();
+            let message = message.lines().next().unwrap_or_default();
+            format!("config: {}", message.trim_start_matches("error: "))
+        })?;
+        Config::from_args(args, config_path)
+    }
+    /// Parse the config file and cli args, cli args taking precedence
+    fn parse_args() -> Result<(Result<Args, clap::Error>, Option<PathBuf>), String> {
+        let mut cli_args: Vec<String> = std::env::args().collect();
+        let config_path = Config::config_path(&cli_args)?;
+
+        // config file options go first, leaving out whatever the cli overrides
+        let mut args: Vec<String> = cli_args.drain(..1).collect();
+        if let Some(config_path) = &config_path {
+            args.extend(Config::config_args(config_path, &cli_args)?);
+        }
+        args.extend(cli_args);
+
+        Ok((Args::try_parse_from(args), config_path))
+    }
+    /// Return the path of the config file to use if there is one
+    fn config_path(cli_args: &[String]) -> Result<Option<PathBuf>, String> {
+        // look for an explicit path before the full parse
+        for (index, arg) in cli_args.iter().enumerate() {
+            let explicit = match arg.as_str() {
+                "-c" | "--config" => cli_args.get(index + 1).cloned(),
+                _ => arg.strip_prefix("--config=").map(str::to_string),
+            };
+            if let Some(path) = explicit {
+                if fs::metadata(&path).is_err() {
+                    return Err(format!("\"{}\": invalid config", path));
+                }
+                return Ok(Some(PathBuf::from(path)));
+            }
+        }
+
+        // fall back to the default location if it exists
+        let config_home = match std::env::var("XDG_CONFIG_HOME") {
+            Ok(config_home) => PathBuf::from(config_home),
+            Err(_) => match std::env::var("HOME") {
+                Ok(home) => PathBuf::from(home).join(".config"),
+                Err(_) => return Ok(None),
+            },
+        };
+        let default_path = config_home.join("rwpspread/config.toml");
+
+        Ok(Some(default_path).filter(|path| path.is_file()))
+    }
+    /// Return the args given on the command line
+    fn cli_given<'a>(command: &'a clap::Command, cli_args: &[String]) -> Vec<&'a clap::Arg> {
+        // only which args are present matters here, the full parse validates them
+        let Ok(matches) = command
+            .clone()
+            .no_binary_name(true)
+            .ignore_errors(true)
+            .try_get_matches_from(cli_args)
+        else {
+            return Vec::new();
+        };
+
+        command
+            .get_arguments()
+            .filter(|arg| {
+                matches.value_source(arg.get_id().as_str())
+                    == Some(clap::parser::ValueSource::CommandLine)
+            })
+            .collect()
+    }
+    /// Check if a config file option is overridden by the args given on the command line
+    fn is_overridden(command: &clap::Command, long: &str, given: &[&clap::Arg]) -> bool {
+        let Some(arg) = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long))
+        else {
+            // unknown keys are left to the full parse to reject
+            return false;
+        };
+
+        // the same option, or one that can't be used together with it
+        let conflicts = |first: &clap::Arg, second: &clap::Arg| {
+            command
+                .get_arg_conflicts_with(first)
+                .iter()
+                .any(|conflict| conflict.get_id() == second.get_id())
+        };
+        given.iter().any(|other| {
+            other.get_id() == arg.get_id() || conflicts(arg, other) || conflicts(other, arg)
+        })
+    }
+    /// Convert the options of a config file to cli args, skipping those the cli overrides
+    fn config_args(
+        config_path: &std::path::Path,
+        cli_args: &[String],
+    ) -> Result<Vec<String>, String> {
+        let content = fs::read_to_string(config_path)
+            .map_err(|_| format!("\"{}\": failed to read config", config_path.display()))?;
+        let table: toml::Table = toml::from_str(&content)
+            .map_err(|err| format!("\"{}\": {}", config_path.display(), err.message()))?;
+
+        let command = <Args as clap::CommandFactory>::command();
+        let given = Config::cli_given(&command, cli_args);
+
+        // keys are the long cli options
+        let mut args: Vec<String> = Vec::new();
+        for (key, value) in table {
+            let long = key.replace('_', "-");
+            if Config::is_overridden(&command, &long, &given) {
+                continue;
+            }
+            let flag = format!("--{}", long);
+            let invalid = format!(
+                "\"{}\": invalid config value for {}",
+                config_path.display(),
+                key
+            );
+            match value {
+                toml::Value::Boolean(true) => args.push(flag),
+                toml::Value::Boolean(false) => {}
+                toml::Value::Array(values) => {
+                    args.push(flag);
+                    for value in values {
+                        args.push(Config::config_value(value).ok_or(invalid.clone())?);
+                    }
+                }
+                value => {
+                    let value = Config::config_value(value).ok_or(invalid)?;
+                    args.push(format!("{}={}", flag, value));
+                }
+            }
+        }
 
+        Ok(args)
+    }
+    /// Convert a single config file value to a cli arg value
+    fn config_value(value: toml::Value) -> Option<String> {
+        match value {
+            toml::Value::String(value) => Some(value),
+            toml::Value::Integer(value) => Some(value.to_string()),
+            toml::Value::Float(value) => Some(value.to_string()),
+            _ => None,
+        }
+    }
+    /// Generate and return a new Config based on parsed args
+    fn from_args(mut args: Args, config_path: Option<PathBuf>) -> Result<Option<Self>, String> {
         // get valid input path
-        if let Some(image_path) = args.init_group.image {
-            let input_paths = Config::to_valid_paths(&image_path, false, false)?;
+        if args.init_group.image.is_some()
+            || args.init_group.image_cmd.is_some()
+            || args.init_group.schedule.is_some()
+        {
+            let mut input_bytes: Option<Vec<u8>> = None;
+            let input_paths = match (&args.init_group.image, &args.init_group.image_cmd) {
+                (Some(image_path), _) if image_path == "-" => {
+                    // image data is piped in
+                    input_bytes = Some(Config::read_stdin()?);
+                    (PathBuf::from("-"), PathBuf::from("-"))
+                }
+                (Some(image_path), _) => Config::to_valid_paths(image_path, false, false)?,
+                (None, Some(command)) => {
+                    // command either prints a path or the image data itself
+                    let output = Config::run_image_cmd(command)?;
+                    match std::str::from_utf8(&output).map(str::trim) {
+                        Ok(path) if !path.is_empty() && fs::metadata(path).is_ok() => {
+                            Config::to_valid_paths(&path.to_string(), false, false)?
+                        }
+                        _ => {
+                            input_bytes = Some(output);
+                            (PathBuf::from("-"), PathBuf::from("-"))
+                        }
+                    }
+                }
+                // resolved from the active schedule slot at runtime
+                _ => (PathBuf::new(), PathBuf::new()),
+            };
+            if args.watch && input_bytes.is_some() {
+                return Err("cannot watch image data that is not on disk".to_string());
+            }
+            if args.backend != Some(Backend::Swww)
+                && (args.transition.is_some()
+                    || args.transition_duration.is_some()
+                    || args.transition_fps.is_some())
+            {
+                return Err("transitions are only supported by the swww backend".to_string());
+            }
+
+            // check for scheduled inputs
+            let mut schedule: Vec<ScheduleEntry> = Vec::new();
+            if let Some(entries) = args.init_group.schedule {
+                for entity in entries {
+                    let (slot, path) = entity
+                        .split_once(":")
+                        .ok_or(format!("\"{}\": invalid schedule entry", entity))?;
+                    let paths = Config::to_valid_paths(&path.trim().to_string(), false, false)?;
+                    schedule.push(ScheduleEntry {
+                        slot: slot.trim().parse()?,
+                        input_path: paths.1,
+                        raw_input_path: paths.0,
+                    });
+                }
+            }
+
+            // check for custom slot start times
+            let mut schedule_times: Vec<(Slot, u32)> = Vec::new();
+            if let Some(times) = args.times {
+                for entity in times {
+                    let invalid = format!("\"{}\": invalid schedule time", entity);
+                    let (slot, time) = entity.split_once(":").ok_or(&invalid)?;
+                    let (hours, minutes) = time.split_once(":").ok_or(&invalid)?;
+                    match (hours.trim().parse::<u32>(), minutes.trim().parse::<u32>()) {
+                        (Ok(hours), Ok(minutes)) if hours < 24 && minutes < 60 => {
+                            schedule_times.push((slot.trim().parse()?, hours * 60 + minutes));
+                        }
+                        _ => return Err(invalid),
+                    }
+                }
+            }
+
+            // check for location coordinates
+            let mut location: Option<(f64, f64)> = None;
+            if let Some(coordinates) = args.location {
+                let invalid = format!("\"{}\": invalid location", coordinates);
+                let (latitude, longitude) = coordinates.split_once(":").ok_or(&invalid)?;
+                match (
+                    latitude.trim().parse::<f64>(),
+                    longitude.trim().parse::<f64>(),
+                ) {
+                    (Ok(latitude), Ok(longitude))
+                        if latitude.abs() <= 90.0 && longitude.abs() <= 180.0 =>
+                    {
+                        location = Some((latitude, longitude));
+                    }
+                    _ => return Err(invalid),
+                }
+            }
 
             // get valid output directory
             if let Some(output_path) = args.output {
@@ -202,51 +780,137 @@ impl Config {
                 }
             }
 
-            // check for scripts
-            if let Some(pre_script_path) = args.pre {
-                args.pre = Some(
-                    Config::to_valid_paths(&pre_script_path, true, false)?
-                        .1
-                        .to_string_lossy()
-                        .to_string(),
-                );
-            } else {
-                args.pre = None;
-            }
-
-            if let Some(post_script_path) = args.post {
-                args.post = Some(
-                    Config::to_valid_paths(&post_script_path, true, false)?
-                        .1
-                        .to_string_lossy()
-                        .to_string(),
-                );
-            } else {
-                args.post = None;
-            }
+            // check for hooks
+            let pre_command = args.pre.map(Config::to_command).transpose()?;
+            let post_command = args.post.map(Config::to_command).transpose()?;
+            let on_hotplug = args.on_hotplug.map(Config::to_command).transpose()?;
+            let on_image_change = args.on_image_change.map(Config::to_command).transpose()?;
+            let on_error = args.on_error.map(Config::to_command).transpose()?;
+            let on_backend_restart = args
+                .on_backend_restart
+                .map(Config::to_command)
+                .transpose()?;
 
             Ok(Some(Self {
                 input_path: input_paths.1,
                 raw_input_path: input_paths.0,
-                diagonals: diagonals,
+                diagonals,
                 output_path: args.output,
                 align: args.align,
                 backend: args.backend,
+                transition: args.transition,
+                transition_duration: args.transition_duration,
+                transition_fps: args.transition_fps,
+                wpaperd_mode: args.wpaperd_mode,
+                hyprpaper_fit: args.hyprpaper_fit,
+                hyprpaper_version: args.hyprpaper_version,
+                custom_cmd: args.custom_cmd,
+                custom_kill: args.custom_kill,
+                custom_long_running: args.custom_long_running,
                 locker: args.locker,
                 bezel: args.bezel,
                 ppi: args.ppi,
-                daemon: args.daemon,
+                daemon: args.daemon && !args.no_daemon,
                 palette: args.palette,
                 force_resplit: args.force_resplit,
                 info: args.init_group.info,
-                pre_path: args.pre,
-                post_path: args.post,
+                pre_command,
+                post_command,
+                hook_timeout: Some(args.hook_timeout).filter(|timeout| *timeout > 0),
+                hook_failure: args.hook_failure,
+                on_hotplug,
+                on_image_change,
+                on_error,
+                on_backend_restart,
                 watch: args.watch,
+                on_change: args.on_change,
+                schedule,
+                schedule_times,
+                location,
+                input_bytes,
+                config_path,
                 version: String::from(env!("CARGO_PKG_VERSION")),
             }))
         } else {
-            return Ok(None);
+            Ok(None)
+        }
+    }
+    /// Return the subcommand if one was given
+    pub fn mode() -> Option<Mode> {
+        // anything else is left to the full parse including the config file
+        Args::try_parse().ok().and_then(|args| args.mode)
+    }
+    /// Return all raw input paths that need to be watched for changes
+    pub fn watch_paths(&self) -> Vec<PathBuf> {
+        if self.schedule.is_empty() {
+            vec![self.raw_input_path.to_owned()]
+        } else {
+            self.schedule
+                .iter()
+                .map(|entry| entry.raw_input_path.to_owned())
+                .collect()
+        }
+    }
+    /// Read and return all image data from stdin, which is only read once
+    fn read_stdin() -> Result<Vec<u8>, String> {
+        static STDIN: std::sync::OnceLock<Vec<u8>> = std::sync::OnceLock::new();
+        if let Some(bytes) = STDIN.get() {
+            return Ok(bytes.to_owned());
+        }
+
+        let mut bytes: Vec<u8> = Vec::new();
+        std::io::stdin()
+            .read_to_end(&mut bytes)
+            .map_err(|_| "failed to read stdin")?;
+        if bytes.is_empty() {
+            return Err("stdin: no image data".to_string());
+        }
+
+        Ok(STDIN.get_or_init(|| bytes).to_owned())
+    }
+    /// Run an image command and return its output
+    fn run_image_cmd(command: &str) -> Result<Vec<u8>, String> {
+        let output = crate::helpers::Helpers::command("sh")
+            .arg("-c")
+            .arg(command)
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::inherit())
+            .output()
+            .map_err(|_| format!("failed to run {}", command))?;
+        if !output.status.success() || output.stdout.is_empty() {
+            return Err(format!("\"{}\": image command failed", command));
         }
+
+        Ok(output.stdout)
+    }
+    /// Check and return if a path points to a playlist file
+    pub fn is_playlist(path: &std::path::Path) -> bool {
+        matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("m3u" | "m3u8" | "txt")
+        )
+    }
+    /// Parse a transition duration, which has to be a positive number of seconds
+    fn to_duration(value: &str) -> Result<f32, String> {
+        match value.parse::<f32>() {
+            Ok(duration) if duration.is_finite() && duration > 0.0 => Ok(duration),
+            _ => Err("expected a positive number of seconds".to_string()),
+        }
+    }
+    /// Return a hook as program and arguments
+    fn to_command(mut command: Vec<String>) -> Result<Vec<String>, String> {
+        if command.len() > 1 {
+            return Ok(command);
+        }
+        let line = command.pop().unwrap_or_default();
+
+        // a lone path has to exist, everything else is a shell command line
+        if !line.contains(char::is_whitespace) && line.contains('/') {
+            let path = Config::to_valid_paths(&line, true, false)?.1;
+            return Ok(vec![path.to_string_lossy().to_string()]);
+        }
+
+        Ok(vec!["sh".to_string(), "-c".to_string(), line])
     }
     // check if path exists correctly and return if true
     fn to_valid_paths(path: &String, file: bool, dir: bool) -> Result<(PathBuf, PathBuf), String> {
@@ -255,15 +919,20 @@ impl Config {
             // canonicalize path so it is absolute
             let abs_path =
                 fs::canonicalize(&raw_path).map_err(|_| "could not canonicalize path")?;
-            if (file || (!dir && !file))
+            if (file || !dir)
                 && fs::metadata(&abs_path)
                     .map_err(|_| "could not get metadata")?
                     .is_file()
             {
+                // playlists need to be readable as text
+                if !file && Config::is_playlist(&abs_path) && fs::read_to_string(&abs_path).is_err()
+                {
+                    return Err(format!("\"{}\": invalid playlist", raw_path.display()));
+                }
                 // valid file
                 return Ok((raw_path, abs_path));
             }
-            if (dir || (!dir && !file))
+            if (dir || !file)
                 && fs::metadata(&abs_path)
                     .map_err(|_| "could not get metadata")?
                     .is_dir()
@@ -0,0 +1,712 @@
+use crate::cli::{Config, Control, HookFailure, OnChange};
+use crate::helpers::Helpers;
+use crate::integrations::Registry;
+use crate::ipc::Ipc;
+use crate::notify::Notify;
+use crate::schedule::Schedule;
+use crate::watch::{FileChange, Watcher};
+use crate::wayland::{ListOutputs, Wayland};
+use crate::worker::{Trigger, Worker};
+use smithay_client_toolkit::reexports::calloop::{
+    EventLoop, Interest, LoopHandle, Mode, PostAction, RegistrationToken,
+    generic::Generic,
+    timer::{TimeoutAction, Timer},
+};
+use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
+use std::fs;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// time without monitor events before a hotplug is considered done
+const HOTPLUG_SETTLE: Duration = Duration::from_millis(750);
+// time without file events before a save is considered done
+const FILE_SETTLE: Duration = Duration::from_millis(250);
+// bounds of the delay between retries, doubled after each failure
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+
+pub struct Daemon {
+    config: Config,
+    worker: Worker,
+    outputs: ListOutputs,
+    monitors: Vec<String>,
+    // image of the last successful run, kept while a new input is tried
+    shown: Option<PathBuf>,
+    handle: LoopHandle<'static, Daemon>,
+    running: bool,
+    wayland: Option<RegistrationToken>,
+    backend_events: Option<(u64, RegistrationToken)>,
+    stale: bool,
+    files: Option<(Vec<PathBuf>, RegistrationToken)>,
+    schedule: Option<RegistrationToken>,
+    rotation: Option<(Instant, RegistrationToken)>,
+    settle: Option<RegistrationToken>,
+    file_settle: Option<RegistrationToken>,
+    retry: Option<RegistrationToken>,
+    restart: Option<RegistrationToken>,
+    followers: Vec<UnixStream>,
+    published: String,
+    retry_delay: Duration,
+    reconnect_delay: Duration,
+    restart_delay: Duration,
+    restarted_at: Option<Instant>,
+    result: Result<(), String>,
+}
+
+impl Daemon {
+    /// Run the daemon until it is stopped or fails
+    pub fn run(config: Config) -> Result<(), String> {
+        // only one daemon per session may manage the cache and backends
+        let _lock = Ipc::lock()?;
+
+        // signals are handled by the event loop, block them before any thread starts
+        let signals = Watcher::signals()?;
+
+        let mut event_loop: EventLoop<Daemon> =
+            EventLoop::try_new().map_err(|_| "event loop: failed to initialize")?;
+        let handle = event_loop.handle();
+        let (outputs, wayland_source) = Wayland::connect()?.into_source()?;
+        let mut daemon = Daemon {
+            config,
+            worker: Worker::new(),
+            outputs,
+            monitors: Vec::new(),
+            shown: None,
+            handle: handle.clone(),
+            running: true,
+            wayland: None,
+            backend_events: None,
+            stale: false,
+            files: None,
+            schedule: None,
+            rotation: None,
+            settle: None,
+            file_settle: None,
+            retry: None,
+            restart: None,
+            followers: Vec::new(),
+            published: String::new(),
+            retry_delay: RETRY_MIN,
+            reconnect_delay: RETRY_MIN,
+            restart_delay: RETRY_MIN,
+            restarted_at: None,
+            result: Ok(()),
+        };
+
+        // run worker initially and keep it for playlist state
+        let monitors = daemon.outputs.monitors()?;
+        daemon.monitors = monitors.iter().map(|mon| mon.name.to_owned()).collect();
+        daemon
+            .worker
+            .run(&daemon.config, monitors, Trigger::Startup)?;
+        daemon.shown = daemon.worker.current().map(|image| image.to_owned());
+        daemon.attach(wayland_source)?;
+        daemon.watch_backend();
+
+        daemon.watch_config()?;
+        daemon.watch_files()?;
+        daemon.schedule()?;
+
+        // failed resplits are already reported and retried
+        handle
+            .insert_source(
+                Generic::new(signals, Interest::READ, Mode::Level),
+                |_, signals, daemon| {
+                    for signal in Watcher::read_signals(signals) {
+                        // a backend process exited, maybe one of ours
+                        if signal == libc::SIGCHLD {
+                            if daemon.worker.reap() {
+                                daemon.supervise();
+                            }
+                            continue;
+                        }
+                        let command = match signal {
+                            libc::SIGHUP => Control::Reload,
+                            libc::SIGUSR1 => Control::Next,
+                            libc::SIGUSR2 => Control::Previous,
+                            _ => Control::Quit,
+                        };
+                        daemon
+                            .control(&command, Trigger::Signal)
+                            .unwrap_or_default();
+                    }
+                    Ok(PostAction::Continue)
+                },
+            )
+            .map_err(|_| "event loop: failed to add signal source")?;
+
+        // failed commands are reported back to the client
+        handle
+            .insert_source(
+                Generic::new(Ipc::listen()?, Interest::READ, Mode::Level),
+                |_, listener, daemon| {
+                    let followers =
+                        Ipc::accept(listener, |command| daemon.control(command, Trigger::Ipc));
+                    daemon.followers.extend(followers);
+                    Ok(PostAction::Continue)
+                },
+            )
+            .map_err(|_| "event loop: failed to add control socket")?;
+
+        // keep the service manager from considering us hung
+        if let Some(interval) = Notify::watchdog() {
+            handle
+                .insert_source(Timer::from_duration(interval), move |_, _, _| {
+                    Notify::send("WATCHDOG=1");
+                    TimeoutAction::ToDuration(interval)
+                })
+                .map_err(|_| "event loop: failed to add watchdog timer")?;
+        }
+        Notify::send(&format!("READY=1\nSTATUS={}", daemon.describe()));
+
+        while daemon.running {
+            if let Err(err) = event_loop.dispatch(None, &mut daemon) {
+                daemon.disconnect(err.to_string());
+            }
+            daemon.rearm();
+            daemon.publish();
+            // a lost connection is replaced on the next run
+            if let Err(err) = daemon.worker.dispatch() {
+                eprintln!("rwpspread: \x1B[91m{}\x1B[39m", err);
+                daemon.watch_backend();
+            }
+        }
+        Notify::send("STOPPING=1");
+        Ipc::cleanup();
+
+        daemon.result
+    }
+    /// Reload the config if it is stale and resplit, retrying on failure
+    fn resplit(&mut self, force: bool, trigger: Trigger) -> Result<(), String> {
+        if std::mem::take(&mut self.stale) {
+            self.reload();
+        }
+        let force_resplit = self.config.force_resplit;
+        self.config.force_resplit |= force;
+        let result = self
+            .outputs
+            .monitors()
+            .and_then(|monitors| self.worker.run(&self.config, monitors, trigger));
+        self.config.force_resplit = force_resplit;
+        self.watch_backend();
+
+        // the last good wallpaper stays in place until a retry succeeds
+        if let Some(token) = self.retry.take() {
+            self.handle.remove(token);
+        }
+        match &result {
+            Ok(_) => {
+                self.retry_delay = RETRY_MIN;
+                Notify::send(&format!("STATUS={}", self.describe()));
+                // setting the same image again is no change
+                let image = self.worker.current().map(|image| image.to_owned());
+                if let Some(image) = image.filter(|image| Some(image) != self.shown.as_ref()) {
+                    let previous = self.shown.replace(image.to_owned());
+                    let mut envs = vec![("RWPS_IMAGE".to_string(), image.display().to_string())];
+                    if let Some(previous) = &previous {
+                        envs.push((
+                            "RWPS_PREVIOUS_IMAGE".to_string(),
+                            previous.display().to_string(),
+                        ));
+                    }
+                    self.hook("image-change", self.config.on_image_change.as_deref(), envs);
+                }
+            }
+            Err(err) => {
+                let delay = Daemon::backoff(&mut self.retry_delay);
+                eprintln!(
+                    "rwpspread: \x1B[91m{}, retrying in {}s\x1B[39m",
+                    err,
+                    delay.as_secs()
+                );
+                Notify::send(&format!("STATUS={}, retrying in {}s", err, delay.as_secs()));
+                let envs = vec![
+                    ("RWPS_ERROR".to_string(), err.to_owned()),
+                    ("RWPS_RETRY_IN".to_string(), delay.as_secs().to_string()),
+                ];
+                self.hook("error", self.config.on_error.as_deref(), envs);
+                let inserted =
+                    self.handle
+                        .insert_source(Timer::from_duration(delay), move |_, _, daemon| {
+                            daemon.retry = None;
+                            daemon.resplit(false, trigger).unwrap_or(());
+                            TimeoutAction::Drop
+                        });
+                match inserted {
+                    Ok(token) => self.retry = Some(token),
+                    Err(_) => self.fail("event loop: failed to add retry timer"),
+                }
+            }
+        }
+
+        result
+    }
+    /// Replace the config with the current one, keeping it if that is invalid
+    fn reload(&mut self) {
+        let config = match Config::reload() {
+            Ok(Some(config)) => config,
+            Ok(None) => {
+                eprintln!(
+                    "rwpspread: \x1B[91mconfig: no input given, keeping previous config\x1B[39m"
+                );
+                return;
+            }
+            Err(err) => {
+                eprintln!(
+                    "rwpspread: \x1B[91m{}, keeping previous config\x1B[39m",
+                    err
+                );
+                return;
+            }
+        };
+        if let Some(backend) = config.backend.as_ref().map(Registry::backend) {
+            if !backend.is_available(&config) {
+                eprintln!(
+                    "rwpspread: \x1B[91m{} is not installed, keeping previous config\x1B[39m",
+                    backend.program(&config).unwrap_or_default()
+                );
+                return;
+            }
+        }
+        self.config = config;
+
+        // inputs and schedule slots may have changed
+        if let Err(err) = self.watch_files().and_then(|_| self.schedule()) {
+            self.fail(&err);
+        }
+    }
+    /// Read the config again once its file changed
+    fn watch_config(&mut self) -> Result<(), String> {
+        // the config path is fixed by the command line, so this watch stays
+        let Some(path) = self.config.config_path.clone() else {
+            return Ok(());
+        };
+
+        self.handle
+            .insert_source(
+                Generic::new(Watcher::file(&[path])?, Interest::READ, Mode::Level),
+                |_, file_watch, daemon| {
+                    // SAFETY: the watch is only read from and updated, never dropped
+                    match unsafe { file_watch.get_mut() }.changes() {
+                        Ok(changes) if !changes.is_empty() => {
+                            daemon.stale = true;
+                            daemon.settle_file();
+                        }
+                        Ok(_) => {}
+                        Err(err) => eprintln!("rwpspread: \x1B[91m{}\x1B[39m", err),
+                    }
+                    Ok(PostAction::Continue)
+                },
+            )
+            .map_err(|_| "event loop: failed to add config watcher")?;
+
+        Ok(())
+    }
+    /// Watch the inputs for changes if enabled
+    fn watch_files(&mut self) -> Result<(), String> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        if self.config.watch {
+            paths = self.config.watch_paths();
+        }
+        if self.files.as_ref().map(|files| &files.0) == Some(&paths) {
+            return Ok(());
+        }
+        if let Some((_, token)) = self.files.take() {
+            self.handle.remove(token);
+        }
+        if paths.is_empty() {
+            return Ok(());
+        }
+
+        let file_watch = Watcher::file(&paths)?;
+        let token = self
+            .handle
+            .insert_source(
+                Generic::new(file_watch, Interest::READ, Mode::Level),
+                |_, file_watch, daemon| {
+                    // SAFETY: the watch is only read from and updated, never dropped
+                    match unsafe { file_watch.get_mut() }.changes() {
+                        Ok(changes) => daemon.apply(changes),
+                        Err(err) => eprintln!("rwpspread: \x1B[91m{}\x1B[39m", err),
+                    }
+                    Ok(PostAction::Continue)
+                },
+            )
+            .map_err(|_| "event loop: failed to add file watcher")?;
+        self.files = Some((paths, token));
+
+        Ok(())
+    }
+    /// Wake up once the next schedule slot starts
+    fn schedule(&mut self) -> Result<(), String> {
+        if let Some(token) = self.schedule.take() {
+            self.handle.remove(token);
+        }
+        let Some(schedule) = Schedule::new(&self.config) else {
+            return Ok(());
+        };
+
+        let token = self
+            .handle
+            .insert_source(
+                Timer::from_duration(schedule.next_change()),
+                |_, _, daemon| {
+                    // a successful reload replaces this timer, which drops it
+                    daemon.resplit(false, Trigger::Timer).unwrap_or(());
+                    match Schedule::new(&daemon.config) {
+                        Some(schedule) => TimeoutAction::ToDuration(schedule.next_change()),
+                        None => TimeoutAction::Drop,
+                    }
+                },
+            )
+            .map_err(|_| "event loop: failed to add schedule timer")?;
+        self.schedule = Some(token);
+
+        Ok(())
+    }
+    /// Handle a control command and return its reply
+    fn control(&mut self, command: &Control, trigger: Trigger) -> Result<String, String> {
+        match command {
+            Control::Next => {
+                self.worker.advance(1);
+                self.resplit(false, trigger)?;
+            }
+            Control::Previous => {
+                self.worker.advance(-1);
+                self.resplit(false, trigger)?;
+            }
+            Control::Set { path } => {
+                if fs::metadata(path).is_err() {
+                    return Err(format!("\"{}\": invalid path", path));
+                }
+                self.worker.set_input(Some(PathBuf::from(path)));
+                self.resplit(false, trigger)?;
+            }
+            Control::Reload => {
+                self.stale = true;
+                self.worker.set_input(None);
+                self.resplit(false, trigger)?;
+            }
+            Control::Status { follow: true } => {
+                // new followers start out with the same line as everybody else
+                self.publish();
+                self.published = serde_json::to_string(&self.worker.status())
+                    .map_err(|_| "serialization error".to_string())?;
+                return Ok(self.published.clone());
+            }
+            Control::Status { follow: false } => {
+                return serde_json::to_string_pretty(&self.worker.status())
+                    .map_err(|_| "serialization error".to_string());
+            }
+            Control::Pause => self.worker.pause(),
+            Control::Resume => self.worker.resume(),
+            Control::Resplit { force } => self.resplit(*force, trigger)?,
+            Control::Quit => self.running = false,
+        }
+
+        Ok(String::new())
+    }
+    /// Stop the daemon with an error
+    fn fail(&mut self, err: &str) {
+        self.result = Err(err.to_string());
+        self.running = false;
+    }
+    /// Return the current retry delay and double it for the next failure
+    fn backoff(delay: &mut Duration) -> Duration {
+        let current = *delay;
+        *delay = (*delay * 2).min(RETRY_MAX);
+
+        current
+    }
+    /// Restart the crashed backend, waiting longer the more often it crashes
+    fn supervise(&mut self) {
+        if self.restart.is_some() {
+            return;
+        }
+        // a backend that kept running for a while starts over
+        if self
+            .restarted_at
+            .is_none_or(|restarted_at| restarted_at.elapsed() > RETRY_MAX)
+        {
+            self.restart_delay = RETRY_MIN;
+        }
+
+        let delay = Daemon::backoff(&mut self.restart_delay);
+        let backend = self.worker.backend().unwrap_or_default();
+        eprintln!(
+            "rwpspread: \x1B[91m{}: exited, restarting in {}s\x1B[39m",
+            backend,
+            delay.as_secs()
+        );
+        let inserted = self
+            .handle
+            .insert_source(Timer::from_duration(delay), |_, _, daemon| {
+                daemon.restart = None;
+                daemon.restarted_at = Some(Instant::now());
+                let restarted = daemon.worker.restart(&daemon.config);
+                daemon.watch_backend();
+                match restarted {
+                    Ok(_) => {
+                        let envs = vec![(
+                            "RWPS_BACKEND".to_string(),
+                            daemon.worker.backend().unwrap_or_default(),
+                        )];
+                        daemon.hook(
+                            "backend-restart",
+                            daemon.config.on_backend_restart.as_deref(),
+                            envs,
+                        );
+                    }
+                    Err(err) => {
+                        eprintln!("rwpspread: \x1B[91m{}\x1B[39m", err);
+                        daemon.supervise();
+                    }
+                }
+                TimeoutAction::Drop
+            });
+        match inserted {
+            Ok(token) => self.restart = Some(token),
+            Err(_) => self.fail("event loop: failed to add restart timer"),
+        }
+    }
+    /// Add a wayland connection to the event loop
+    fn attach(&mut self, source: WaylandSource<ListOutputs>) -> Result<(), String> {
+        // output changes are only applied once they have settled
+        let token = self
+            .handle
+            .insert_source(source, |_, queue, daemon| {
+                let result = queue.dispatch_pending(&mut daemon.outputs);
+                if daemon.outputs.take_changed() {
+                    daemon.settle();
+                }
+                result
+            })
+            .map_err(|_| "event loop: failed to add wayland source")?;
+        self.wayland = Some(token);
+
+        Ok(())
+    }
+    /// Wake up for events of the backend, following it across reconnects
+    fn watch_backend(&mut self) {
+        let connection = self.worker.connection();
+        if self.backend_events.as_ref().map(|events| events.0) == connection {
+            return;
+        }
+        // the copy keeps a replaced connection open, drop it
+        if let Some((_, token)) = self.backend_events.take() {
+            self.handle.remove(token);
+        }
+        let (Some(connection), Some(fd)) = (connection, self.worker.poll_fd()) else {
+            return;
+        };
+
+        // the events are read right after the loop wakes up
+        let inserted = self
+            .handle
+            .insert_source(Generic::new(fd, Interest::READ, Mode::Level), |_, _, _| {
+                Ok(PostAction::Continue)
+            });
+        match inserted {
+            Ok(token) => self.backend_events = Some((connection, token)),
+            Err(_) => self.fail("event loop: failed to add backend source"),
+        }
+    }
+    /// Drop a failed wayland connection and try to connect again
+    fn disconnect(&mut self, err: String) {
+        // only the wayland connection can fail while dispatching
+        let Some(token) = self.wayland.take() else {
+            self.result = Err(format!("event loop: {}", err));
+            self.running = false;
+            return;
+        };
+        self.handle.remove(token);
+        eprintln!("rwpspread: \x1B[91mwayland: connection lost, reconnecting\x1B[39m");
+        self.reconnect();
+    }
+    /// Reconnect to the compositor, retrying on failure
+    fn reconnect(&mut self) {
+        match Wayland::connect().and_then(|wayland| wayland.into_source()) {
+            Ok((outputs, source)) => {
+                self.outputs = outputs;
+                self.reconnect_delay = RETRY_MIN;
+                if let Err(err) = self.attach(source) {
+                    self.fail(&err);
+                    return;
+                }
+                // outputs may have changed while we were gone
+                self.settle();
+            }
+            Err(_) => {
+                let delay = Daemon::backoff(&mut self.reconnect_delay);
+                let inserted =
+                    self.handle
+                        .insert_source(Timer::from_duration(delay), |_, _, daemon| {
+                            daemon.reconnect();
+                            TimeoutAction::Drop
+                        });
+                if inserted.is_err() {
+                    self.fail("event loop: failed to add reconnect timer");
+                }
+            }
+        }
+    }
+    /// Restart the settle window of output changes
+    fn settle(&mut self) {
+        if let Some(token) = self.settle.take() {
+            self.handle.remove(token);
+        }
+
+        let inserted =
+            self.handle
+                .insert_source(Timer::from_duration(HOTPLUG_SETTLE), |_, _, daemon| {
+                    // half configured outputs may miss info, retry later
+                    match daemon.outputs.monitors() {
+                        Ok(monitors) if !monitors.is_empty() => {
+                            daemon.settle = None;
+                            if !daemon.worker.is_applied(&monitors) {
+                                daemon.resplit(false, Trigger::Hotplug).unwrap_or(());
+                            }
+                            daemon
+                                .hotplug(monitors.iter().map(|mon| mon.name.to_owned()).collect());
+                            TimeoutAction::Drop
+                        }
+                        _ => TimeoutAction::ToDuration(HOTPLUG_SETTLE),
+                    }
+                });
+        match inserted {
+            Ok(token) => self.settle = Some(token),
+            Err(_) => self.fail("event loop: failed to add settle timer"),
+        }
+    }
+    /// Report outputs that were added or removed since the last time
+    fn hotplug(&mut self, monitors: Vec<String>) {
+        let added: Vec<&str> = monitors
+            .iter()
+            .filter(|name| !self.monitors.contains(name))
+            .map(|name| name.as_str())
+            .collect();
+        let removed: Vec<&str> = self
+            .monitors
+            .iter()
+            .filter(|name| !monitors.contains(name))
+            .map(|name| name.as_str())
+            .collect();
+        if !added.is_empty() || !removed.is_empty() {
+            let envs = vec![
+                ("RWPS_ADDED".to_string(), added.join(" ")),
+                ("RWPS_REMOVED".to_string(), removed.join(" ")),
+                ("RWPS_OUTPUTS".to_string(), monitors.join(" ")),
+            ];
+            self.hook("hotplug", self.config.on_hotplug.as_deref(), envs);
+        }
+        self.monitors = monitors;
+    }
+    /// Run the hook of a daemon event, failures are only reported
+    fn hook(&self, event: &str, command: Option<&[String]>, mut envs: Vec<(String, String)>) {
+        let Some(command) = command else {
+            return;
+        };
+
+        envs.push(("RWPS_EVENT".to_string(), event.to_string()));
+        let timeout = self.config.hook_timeout.map(Duration::from_secs);
+        if let Err(err) = Helpers::run_hook(&format!("on-{}", event), command, &envs, timeout) {
+            if self.config.hook_failure != HookFailure::Ignore {
+                eprintln!("rwpspread: \x1B[91m{}\x1B[39m", err);
+            }
+        }
+    }
+    /// Apply file system changes of the watched inputs
+    fn apply(&mut self, changes: Vec<FileChange>) {
+        let mut resplit = false;
+        for change in changes {
+            match change {
+                FileChange::Added(image) => {
+                    self.worker.image_added(&image);
+                    match self.config.on_change {
+                        OnChange::Switch => self.worker.show(image),
+                        OnChange::Randomize => self.worker.advance(1),
+                        OnChange::Rescan => continue,
+                    }
+                    resplit = true;
+                }
+                FileChange::Removed(image) => {
+                    // a removed image is only missed while it is shown
+                    resplit |= self.worker.is_current(&image);
+                    self.worker.image_removed(&image);
+                }
+                FileChange::Changed => resplit = true,
+            }
+        }
+
+        if resplit {
+            self.settle_file();
+        }
+    }
+    /// Restart the settle window of file changes
+    fn settle_file(&mut self) {
+        if let Some(token) = self.file_settle.take() {
+            self.handle.remove(token);
+        }
+
+        let inserted =
+            self.handle
+                .insert_source(Timer::from_duration(FILE_SETTLE), |_, _, daemon| {
+                    daemon.file_settle = None;
+                    daemon.resplit(false, Trigger::File).unwrap_or(());
+                    TimeoutAction::Drop
+                });
+        match inserted {
+            Ok(token) => self.file_settle = Some(token),
+            Err(_) => self.fail("event loop: failed to add settle timer"),
+        }
+    }
+    /// Match the rotation timer to the current playlist item
+    fn rearm(&mut self) {
+        // a pending retry takes care of the next image
+        let rotate_at = self.worker.rotate_at().filter(|_| self.retry.is_none());
+        if self.rotation.as_ref().map(|rotation| rotation.0) == rotate_at {
+            return;
+        }
+        if let Some((_, token)) = self.rotation.take() {
+            self.handle.remove(token);
+        }
+
+        // wake up once the current playlist item expires
+        if let Some(instant) = rotate_at {
+            let inserted =
+                self.handle
+                    .insert_source(Timer::from_deadline(instant), |_, _, daemon| {
+                        daemon.rotation = None;
+                        daemon.worker.advance(1);
+                        daemon.resplit(false, Trigger::Timer).unwrap_or(());
+                        TimeoutAction::Drop
+                    });
+            match inserted {
+                Ok(token) => self.rotation = Some((instant, token)),
+                Err(_) => self.fail("event loop: failed to add rotation timer"),
+            }
+        }
+    }
+    /// Return a one line summary of what is currently shown
+    fn describe(&self) -> String {
+        let status = self.worker.status();
+        match status.slot {
+            Some(slot) => format!("showing {} ({} slot)", status.text, slot),
+            None => format!("showing {}", status.text),
+        }
+    }
+    /// Send the status to all followers if it changed
+    fn publish(&mut self) {
+        if self.followers.is_empty() {
+            return;
+        }
+        let Ok(line) = serde_json::to_string(&self.worker.status()) else {
+            return;
+        };
+        if line != self.published {
+            Ipc::publish(&mut self.followers, &line);
+            self.published = line;
+        }
+    }
+}
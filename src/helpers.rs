@@ -1,67 +1,105 @@
+use std::io::Read;
+use std::mem;
+use std::os::unix::process::CommandExt;
+use std::ptr;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 use std::{env, process};
 
+// how often a running hook is checked on
+const HOOK_POLL: Duration = Duration::from_millis(10);
+
 pub struct Helpers;
 impl Helpers {
-    // run a one-shot command
-    pub fn run_oneshot(program: &str) -> Result<(), String> {
-        match process::Command::new(program)
+    /// Run a hook command, killing it and everything it started after the timeout
+    pub fn run_hook(
+        name: &str,
+        command: &[String],
+        envs: &[(String, String)],
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        let Some((program, arguments)) = command.split_first() else {
+            return Ok(());
+        };
+        let mut child = Helpers::command(program)
+            .args(arguments)
+            .envs(envs.iter().cloned())
+            .stdin(process::Stdio::null())
             .stdout(process::Stdio::null())
-            .stderr(process::Stdio::null())
-            .output()
-        {
-            Ok(_) => Ok(()),
-            Err(_) => return Err(format!("failed to run {}", program)),
+            .stderr(process::Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .map_err(|_| format!("{}: failed to run {}", name, program))?;
+
+        // collect stderr on the side, a full pipe would stall the hook
+        let (sender, receiver) = mpsc::channel::<Vec<u8>>();
+        if let Some(mut stderr) = child.stderr.take() {
+            thread::Builder::new()
+                .name("rwp_hook".to_string())
+                .spawn(move || {
+                    let mut buffer = [0; 4096];
+                    while let Ok(count) = stderr.read(&mut buffer) {
+                        if count == 0 || sender.send(buffer[..count].to_vec()).is_err() {
+                            break;
+                        }
+                    }
+                })
+                .map_err(|_| format!("{}: failed to read output", name))?;
         }
-    }
 
-    /// Check and return if a program pid given its name exists
-    pub fn pid_exists(program: &str) -> Result<bool, String> {
-        match process::Command::new("pidof")
-            .arg(program)
-            .stdout(process::Stdio::null())
-            .status()
-        {
-            Ok(status) => {
-                return Ok(status.success());
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if deadline.is_some_and(|deadline| Instant::now() >= deadline) => {
+                    // SAFETY: only signals the process group created for the hook
+                    unsafe { libc::kill(-(child.id() as i32), libc::SIGKILL) };
+                    child.wait().unwrap_or_default();
+                    break None;
+                }
+                Ok(None) => thread::sleep(HOOK_POLL),
+                Err(_) => return Err(format!("{}: failed to wait for {}", name, program)),
             }
-            Err(_) => return Err("pidof failed".to_string()),
-        }
-    }
+        };
 
-    /// Force restart a program given its name
-    pub fn force_restart(program: &str, arguments: Vec<&str>) -> Result<(), String> {
-        if Self::pid_exists(program)? {
-            process::Command::new("pkill")
-                .arg(program)
-                .stdout(process::Stdio::null())
-                .output()
-                .map_err(|_| format!("pkill failed: {}", program))?;
+        // programs started in the background may hold on to stderr, don't wait for them
+        let mut output: Vec<u8> = Vec::new();
+        while let Ok(chunk) = receiver.recv_timeout(HOOK_POLL) {
+            output.extend(chunk);
         }
+        let output = String::from_utf8_lossy(&output).trim().to_string();
 
-        process::Command::new(program)
-            .args(arguments)
-            .stdout(process::Stdio::null())
-            .stderr(process::Stdio::null())
-            .spawn()
-            .map_err(|_| format!("failed to spawn: {}", program))?;
-
-        Ok(())
+        let reason = match status {
+            Some(status) if status.success() => return Ok(()),
+            Some(status) => match status.code() {
+                Some(code) => format!("exited with {}", code),
+                None => "was killed".to_string(),
+            },
+            None => format!("timed out after {}s", timeout.unwrap_or_default().as_secs()),
+        };
+        if output.is_empty() {
+            Err(format!("{}: {} {}", name, program, reason))
+        } else {
+            Err(format!("{}: {} {}: {}", name, program, reason, output))
+        }
     }
 
-    /// Soft restart a program given its name
-    pub fn soft_restart(program: &str, arguments: Vec<&str>) -> Result<(), String> {
-        if !Self::pid_exists(program)? {
-            process::Command::new(program)
-                .args(arguments)
-                .stdout(process::Stdio::null())
-                .stderr(process::Stdio::null())
-                .spawn()
-                .map_err(|_| format!("failed to spawn: {}", program))?;
+    /// Return a command for a program, started without the signals the daemon blocks
+    pub fn command(program: &str) -> process::Command {
+        let mut command = process::Command::new(program);
+        // SAFETY: only async-signal-safe calls happen between fork and exec
+        unsafe {
+            command.pre_exec(|| {
+                let mut set: libc::sigset_t = mem::zeroed();
+                libc::sigemptyset(&mut set);
+                libc::sigprocmask(libc::SIG_SETMASK, &set, ptr::null_mut());
+                Ok(())
+            });
         }
 
-        Ok(())
+        command
     }
-
     /// Check if a program is available in $PATH given its name
     pub fn is_installed(program: &str) -> bool {
         if let Some(path) = env::var_os("PATH") {
@@ -76,6 +114,21 @@ impl Helpers {
         false
     }
 
+    /// Parse and return a duration given as seconds or with a s, m or h suffix
+    pub fn parse_duration(input: &str) -> Option<Duration> {
+        let input = input.trim();
+        let (amount, multiplier) = match input.chars().last()? {
+            's' => (&input[..input.len() - 1], 1),
+            'm' => (&input[..input.len() - 1], 60),
+            'h' => (&input[..input.len() - 1], 3600),
+            _ => (input, 1),
+        };
+
+        match amount.parse::<u64>() {
+            Ok(amount) if amount > 0 => Some(Duration::from_secs(amount * multiplier)),
+            _ => None,
+        }
+    }
     /// Round to nearest integer that is divisable by two
     pub fn round_2(n: u32) -> u32 {
         if n % 2 == 0 { n } else { n - 1 }
@@ -0,0 +1,167 @@
+use super::Backend;
+use crate::cli::Config;
+use crate::helpers::Helpers;
+use crate::supervisor::Supervisor;
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub struct Custom {
+    supervisor: Supervisor,
+    // hash and template of the last successful run
+    applied: Option<(String, String)>,
+}
+
+impl Custom {
+    pub fn new() -> Self {
+        Self {
+            supervisor: Supervisor::new("custom"),
+            applied: None,
+        }
+    }
+    /// Return the shell command lines of a template, one per output unless it uses {all}
+    fn commands(template: &str, wallpapers: &HashMap<String, String>) -> Vec<String> {
+        let mut names: Vec<&String> = wallpapers.keys().collect();
+        names.sort();
+
+        if template.contains("{all}") {
+            let all: Vec<String> = names
+                .iter()
+                .map(|name| Custom::quote(&format!("{}:{}", name, wallpapers[*name])))
+                .collect();
+            return vec![template.replace("{all}", &all.join(" "))];
+        }
+        names
+            .iter()
+            .map(|name| {
+                template
+                    .replace("{output}", &Custom::quote(name))
+                    .replace("{path}", &Custom::quote(&wallpapers[*name]))
+            })
+            .collect()
+    }
+    /// Quote a value for the shell
+    fn quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+    /// Run a custom command template for new wallpapers
+    fn run(
+        &mut self,
+        template: &str,
+        wallpapers: &HashMap<String, String>,
+        kill: bool,
+        long_running: bool,
+        timeout: Option<Duration>,
+    ) -> Result<(), String> {
+        // stop whatever we started for the previous wallpaper
+        if kill {
+            self.supervisor.stop()?;
+        }
+
+        for command in Custom::commands(template, wallpapers) {
+            if long_running {
+                self.supervisor
+                    .spawn(Helpers::command("sh").arg("-c").arg(&command))?;
+            } else {
+                Helpers::run_hook(
+                    "custom",
+                    &["sh".to_string(), "-c".to_string(), command],
+                    &[],
+                    timeout,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Backend for Custom {
+    fn program(&self, config: &Config) -> Option<String> {
+        config
+            .custom_cmd
+            .as_ref()?
+            .split_whitespace()
+            .next()
+            .map(str::to_string)
+    }
+    fn is_current(&self, config: &Config, hash: &str) -> Result<bool, String> {
+        // nothing outside of this process tells what a template has shown
+        let Some((applied_hash, template)) = &self.applied else {
+            return Ok(false);
+        };
+        if applied_hash != hash || Some(template) != config.custom_cmd.as_ref() {
+            return Ok(false);
+        }
+
+        // long running setters may need to come back after a crash
+        Ok(!config.custom_long_running || self.supervisor.is_running())
+    }
+    fn apply(
+        &mut self,
+        config: &Config,
+        outputs: &HashMap<String, String>,
+        hash: &str,
+    ) -> Result<(), String> {
+        let template = config.custom_cmd.clone().unwrap_or_default();
+        self.applied = None;
+        self.run(
+            &template,
+            outputs,
+            config.custom_kill,
+            config.custom_long_running,
+            config.hook_timeout.map(Duration::from_secs),
+        )?;
+        self.applied = Some((hash.to_string(), template));
+
+        Ok(())
+    }
+    fn reap(&mut self) -> bool {
+        self.supervisor.reap()
+    }
+    fn shutdown(&mut self) {
+        self.applied = None;
+        self.supervisor.stop().unwrap_or(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    fn wallpapers() -> HashMap<String, String> {
+        HashMap::from([
+            ("HDMI-A-1".to_string(), "/cache/b.png".to_string()),
+            ("DP-1".to_string(), "/cache/it's a.png".to_string()),
+        ])
+    }
+
+    #[test]
+    fn expands_per_output() {
+        assert_eq!(
+            Custom::commands("setter -o {output} {path}", &wallpapers()),
+            vec![
+                "setter -o 'DP-1' '/cache/it'\\''s a.png'",
+                "setter -o 'HDMI-A-1' '/cache/b.png'",
+            ]
+        );
+    }
+
+    #[test]
+    fn expands_all_at_once() {
+        assert_eq!(
+            Custom::commands("setter {all}", &wallpapers()),
+            vec!["setter 'DP-1:/cache/it'\\''s a.png' 'HDMI-A-1:/cache/b.png'"]
+        );
+    }
+
+    #[test]
+    fn quotes_for_the_shell() {
+        let output = process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("printf %s {}", Custom::quote("a 'b' $c \\d")))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "a 'b' $c \\d");
+    }
+}
@@ -1,10 +1,14 @@
+use super::LockerConfig;
 use std::collections::HashMap;
 use std::fs;
+use std::path::PathBuf;
 
 pub struct Hyprlock;
-impl Hyprlock {
-    /// Generate and save new Hyprlock config to disk
-    pub fn new(path: &String, wallpapers: &HashMap<String, String>) -> Result<(), String> {
+impl LockerConfig for Hyprlock {
+    fn path(&self, workdir: &str) -> PathBuf {
+        PathBuf::from(format!("{}/rwps_hyprlock.conf", workdir))
+    }
+    fn generate(&self, workdir: &str, wallpapers: &HashMap<String, String>) -> Result<(), String> {
         let mut base_string = String::new();
         for paper in wallpapers {
             // https://wiki.hyprland.org/Hypr-Ecosystem/hyprlock/#background
@@ -13,8 +17,7 @@ impl Hyprlock {
                 paper.0, paper.1
             );
         }
-        fs::write(format!("{}/rwps_hyprlock.conf", path), base_string)
-            .map_err(|err| err.to_string())?;
+        fs::write(self.path(workdir), base_string).map_err(|err| err.to_string())?;
 
         Ok(())
     }
@@ -1,16 +1,225 @@
+use super::Backend;
+use crate::cli::{Config, HyprpaperFit};
+use crate::helpers::Helpers;
+use crate::supervisor::Supervisor;
 use hyprwire_rs::client::HyprWireClient;
 use hyprwire_rs::wire;
 use std::collections::HashMap;
 use std::env;
+use std::os::unix::net::UnixStream;
 use std::thread;
 use std::time::Duration;
 
-const SUPPORTED_VERSION: u32 = 1;
+// version of the hyprwire transport, the protocol version is chosen on bind
+const WIRE_VERSION: u32 = 1;
+const PROTOCOL: &str = "hyprpaper_core";
+// requests of the manager and wallpaper objects
+const MANAGER_GET_WALLPAPER: u32 = 0;
+const WALLPAPER_PATH: u32 = 0;
+const WALLPAPER_FIT_MODE: u32 = 1;
+const WALLPAPER_MONITOR: u32 = 2;
+const WALLPAPER_APPLY: u32 = 3;
+const WALLPAPER_DESTROY: u32 = 4;
+// events of wallpaper objects
+const WALLPAPER_SUCCESS: u32 = 0;
+const WALLPAPER_FAILED: u32 = 1;
+
+// a connection to hyprpaper, images stay loaded as long as their objects live
+struct Session {
+    client: HyprWireClient,
+    manager: u32,
+    // the wallpaper object shown on each output
+    objects: HashMap<String, u32>,
+}
+
+impl Session {
+    /// Connect, waiting for hyprpaper to come up, and bind the protocol at a version
+    fn connect(socket_path: &str, version: u32) -> Result<Self, String> {
+        // block till we can connect or met retry limit
+        let mut client = None;
+        for _ in 0..40 {
+            match HyprWireClient::connect(socket_path) {
+                Ok(connected) => {
+                    client = Some(connected);
+                    break;
+                }
+                Err(_) => thread::sleep(Duration::from_millis(250)),
+            }
+        }
+        let mut client = client.ok_or("hyprpaper: connection timeout reached")?;
+
+        let protocols = client
+            .perform_handshake(WIRE_VERSION)
+            .map_err(|err| format!("hyprpaper: {}", err))?;
+        let protocol = protocols
+            .iter()
+            .find(|protocol| protocol.spec == PROTOCOL)
+            .ok_or(format!("hyprpaper: {} is not offered", PROTOCOL))?;
+        if protocol.version < version {
+            return Err(format!(
+                "hyprpaper: {} version {} is older than {}",
+                PROTOCOL, protocol.version, version
+            ));
+        }
+        let mut session = Self {
+            client,
+            manager: 0,
+            objects: HashMap::new(),
+        };
+
+        // bind the version asked for, not just the first one
+        let sequence = session.client.get_sequence();
+        session
+            .client
+            .send_message(
+                wire::Code::HW_BIND_PROTOCOL,
+                &[
+                    wire::Value::Uint(sequence),
+                    wire::Value::Varchar(PROTOCOL.to_string()),
+                    wire::Value::Uint(version),
+                ],
+            )
+            .map_err(|err| format!("hyprpaper: {}", err))?;
+        let response = session.receive(None)?;
+        let Some(wire::Value::Uint(manager)) = response.args.first() else {
+            return Err("hyprpaper: expected protocol object".to_string());
+        };
+        session.manager = *manager;
+
+        Ok(session)
+    }
+    /// Apply every wallpaper through its own object, returning the outputs that failed
+    fn apply(
+        &mut self,
+        wallpapers: &HashMap<String, String>,
+        fit: HyprpaperFit,
+    ) -> Result<Vec<String>, String> {
+        let mut failures: Vec<String> = Vec::new();
+        let mut names: Vec<&String> = wallpapers.keys().collect();
+        names.sort();
+        for name in names {
+            // request the new object
+            self.send(self.manager, MANAGER_GET_WALLPAPER, vec![])?;
+            let response = self.receive(None)?;
+            let Some(wire::Value::Uint(object)) = response.args.first() else {
+                return Err("hyprpaper: expected new wallpaper object".to_string());
+            };
+            let object = *object;
+
+            self.send(
+                object,
+                WALLPAPER_PATH,
+                vec![wire::Value::Varchar(wallpapers[name].to_string())],
+            )?;
+            self.send(
+                object,
+                WALLPAPER_MONITOR,
+                vec![wire::Value::Varchar(name.to_string())],
+            )?;
+            self.send(
+                object,
+                WALLPAPER_FIT_MODE,
+                vec![wire::Value::Uint(fit as u32)],
+            )?;
+            self.send(object, WALLPAPER_APPLY, vec![])?;
+
+            // every apply is answered with success or the reason it failed
+            let reply = self.receive(Some(object))?;
+            match (reply.args.get(1), reply.args.get(2)) {
+                (Some(wire::Value::Uint(WALLPAPER_SUCCESS)), _) => {
+                    // the previous image is only released once the new one shows
+                    if let Some(previous) = self.objects.insert(name.to_string(), object) {
+                        self.send(previous, WALLPAPER_DESTROY, vec![])?;
+                    }
+                }
+                (Some(wire::Value::Uint(WALLPAPER_FAILED)), error) => {
+                    let reason = match error {
+                        Some(wire::Value::Uint(0)) => "invalid path",
+                        Some(wire::Value::Uint(1)) => "invalid monitor",
+                        _ => "unknown error",
+                    };
+                    failures.push(format!("{}: {}", name, reason));
+                    self.send(object, WALLPAPER_DESTROY, vec![])?;
+                }
+                _ => return Err("hyprpaper: unexpected reply".to_string()),
+            }
+        }
+
+        // outputs that are gone don't need their images anymore
+        let stale: Vec<String> = self
+            .objects
+            .keys()
+            .filter(|name| !wallpapers.contains_key(*name))
+            .cloned()
+            .collect();
+        for name in stale {
+            if let Some(object) = self.objects.remove(&name) {
+                self.send(object, WALLPAPER_DESTROY, vec![])?;
+            }
+        }
+
+        Ok(failures)
+    }
+    /// Send a request to an object
+    fn send(
+        &mut self,
+        object: u32,
+        method: u32,
+        arguments: Vec<wire::Value>,
+    ) -> Result<(), String> {
+        let mut message = vec![wire::Value::Object(object), wire::Value::Uint(method)];
+        message.extend(arguments);
+        message.push(wire::Value::Seq(self.client.get_sequence()));
+
+        self.client
+            .send_message(wire::Code::HW_GENERIC_PROTOCOL_MESSAGE, &message)
+            .map_err(|err| format!("hyprpaper: {}", err))
+    }
+    /// Receive the next new object or the next event of an object, skipping others
+    fn receive(&mut self, object: Option<u32>) -> Result<wire::Message, String> {
+        loop {
+            let message = self
+                .client
+                .read_message()
+                .map_err(|err| format!("hyprpaper: {}", err))?;
+            match message.code {
+                wire::Code::HW_FATAL_PROTOCOL_ERROR => {
+                    return Err(match message.args.get(2) {
+                        Some(wire::Value::Varchar(error)) => format!("hyprpaper: {}", error),
+                        _ => "hyprpaper: protocol error".to_string(),
+                    });
+                }
+                wire::Code::HW_NEW_OBJECT if object.is_none() => return Ok(message),
+                wire::Code::HW_GENERIC_PROTOCOL_MESSAGE
+                    if object.is_some()
+                        && matches!(
+                            message.args.first(),
+                            Some(wire::Value::Object(id)) if Some(*id) == object
+                        ) =>
+                {
+                    return Ok(message);
+                }
+                // outputs coming and going are announced on the manager
+                _ => {}
+            }
+        }
+    }
+}
+
+pub struct Hyprpaper {
+    supervisor: Supervisor,
+    session: Option<Session>,
+}
 
-pub struct Hyprpaper;
 impl Hyprpaper {
-    /// Generate and push appropriate socket commands to hyprpaper given new input wallpapers
-    pub fn push(wallpapers: &HashMap<String, String>) -> Result<(), String> {
+    pub fn new() -> Self {
+        Self {
+            supervisor: Supervisor::new("hyprpaper"),
+            session: None,
+        }
+    }
+    /// Return the socket path hyprpaper listens on
+    fn socket_path() -> Result<String, String> {
         // find socket base with fallback
         let socket_base: String;
         if let Ok(xdg_dir) = env::var("XDG_RUNTIME_DIR") {
@@ -22,106 +231,285 @@ impl Hyprpaper {
         }
 
         // set target socket with fallback
-        let target_socket: String;
         if let Ok(instance_id) = env::var("HYPRLAND_INSTANCE_SIGNATURE") {
-            target_socket = format!("{}/hypr/{}/.hyprpaper.sock", socket_base, instance_id)
+            Ok(format!(
+                "{}/hypr/{}/.hyprpaper.sock",
+                socket_base, instance_id
+            ))
         } else {
-            target_socket = format!("{}/hypr/.hyprpaper.sock", socket_base)
+            Ok(format!("{}/hypr/.hyprpaper.sock", socket_base))
+        }
+    }
+    /// Push new wallpapers to hyprpaper, keeping the connection for the next push
+    fn push(
+        &mut self,
+        socket_path: &str,
+        wallpapers: &HashMap<String, String>,
+        fit: HyprpaperFit,
+        version: u32,
+    ) -> Result<(), String> {
+        // a lost connection only shows when using it, hyprpaper may have restarted
+        let reused = self
+            .session
+            .as_mut()
+            .map(|session| session.apply(wallpapers, fit));
+        let failures = match reused {
+            Some(Ok(failures)) => failures,
+            _ => {
+                self.session = None;
+                let mut session = Session::connect(socket_path, version)?;
+                let failures = session.apply(wallpapers, fit)?;
+                self.session = Some(session);
+                failures
+            }
+        };
+        if !failures.is_empty() {
+            return Err(format!("hyprpaper: {}", failures.join(", ")));
         }
 
-        // block till we can connect or met retry limit
-        for _ in 0..40 {
-            match HyprWireClient::connect(&target_socket) {
-                Ok(mut client) => {
-                    // perform the handshake
-                    let protocols = client.perform_handshake(SUPPORTED_VERSION)?;
-
-                    // bind to first available protocol
-                    let object_id: u32;
-                    if let Some(protocol) = protocols.first() {
-                        object_id = client.bind_protocol(&protocol.spec)?;
-                    } else {
-                        return Err("no protocol to bind to".to_string());
-                    }
+        Ok(())
+    }
+}
 
-                    // apply for every wallpaper
-                    for paper in wallpapers {
-                        // request the new object
-                        client.send_message(
-                            wire::Code::HW_GENERIC_PROTOCOL_MESSAGE,
-                            &[
-                                wire::Value::Object(object_id),
-                                wire::Value::Uint(0),
-                                wire::Value::Seq(client.get_sequence()),
-                            ],
-                        )?;
-
-                        // process new object handle
-                        let response = client.read_message()?;
-                        if response.code != wire::Code::HW_NEW_OBJECT {
-                            return Err(
-                                "expected new object response after object request".to_string()
-                            );
-                        }
-                        let Some(wire::Value::Uint(hp_object_id)) = response.args.get(0) else {
-                            return Err(
-                                "expected new object for hyprpaper wallpaper object".to_string()
-                            );
-                        };
-
-                        // set wallpaper path
-                        client.send_message(
-                            wire::Code::HW_GENERIC_PROTOCOL_MESSAGE,
-                            &[
-                                wire::Value::Object(*hp_object_id),
-                                wire::Value::Uint(0),
-                                wire::Value::Varchar(paper.1.to_string()),
-                                wire::Value::Seq(client.get_sequence()),
-                            ],
-                        )?;
-                        // set monitor
-                        client.send_message(
-                            wire::Code::HW_GENERIC_PROTOCOL_MESSAGE,
-                            &[
-                                wire::Value::Object(*hp_object_id),
-                                wire::Value::Uint(2),
-                                wire::Value::Varchar(paper.0.to_string()),
-                                wire::Value::Seq(client.get_sequence()),
-                            ],
-                        )?;
-                        // set fit mode
-                        client.send_message(
-                            wire::Code::HW_GENERIC_PROTOCOL_MESSAGE,
-                            &[
-                                wire::Value::Object(*hp_object_id),
-                                wire::Value::Uint(1),
-                                wire::Value::Uint(1),
-                                wire::Value::Seq(client.get_sequence()),
-                            ],
-                        )?;
-                        // apply
-                        client.send_message(
-                            wire::Code::HW_GENERIC_PROTOCOL_MESSAGE,
-                            &[
-                                wire::Value::Object(*hp_object_id),
-                                wire::Value::Uint(3),
-                                wire::Value::Seq(client.get_sequence()),
-                            ],
-                        )?;
-                        _ = client.read_message()?;
-                    }
+impl Backend for Hyprpaper {
+    fn program(&self, _config: &Config) -> Option<String> {
+        Some("hyprpaper".to_string())
+    }
+    fn is_current(&self, _config: &Config, _hash: &str) -> Result<bool, String> {
+        // hyprpaper can't tell what it shows, always push
+        Ok(false)
+    }
+    fn apply(
+        &mut self,
+        config: &Config,
+        outputs: &HashMap<String, String>,
+        _hash: &str,
+    ) -> Result<(), String> {
+        // only start hyprpaper if nobody else did
+        let socket_path = Hyprpaper::socket_path()?;
+        if !self.supervisor.is_running() && UnixStream::connect(&socket_path).is_err() {
+            self.supervisor.spawn(&mut Helpers::command("hyprpaper"))?;
+        }
+        self.push(
+            &socket_path,
+            outputs,
+            config.hyprpaper_fit,
+            config.hyprpaper_version,
+        )
+    }
+    fn reap(&mut self) -> bool {
+        self.supervisor.reap()
+    }
+    fn shutdown(&mut self) {
+        self.session = None;
+        self.supervisor.stop().unwrap_or(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixListener;
+    use std::process;
+
+    // what the fake hyprpaper was asked to do
+    #[derive(Default)]
+    struct Requests {
+        greeting: Option<String>,
+        bound: Option<(String, u32)>,
+        fits: Vec<u32>,
+        destroyed: Vec<u32>,
+    }
+
+    /// Read a message as its code and plain values
+    fn read(stream: &mut UnixStream) -> Option<(u8, Vec<wire::Value>)> {
+        let mut byte = [0; 1];
+        stream.read_exact(&mut byte).ok()?;
+        let code = byte[0];
+        let mut values: Vec<wire::Value> = Vec::new();
+        loop {
+            stream.read_exact(&mut byte).unwrap();
+            let mut number = [0; 4];
+            match byte[0] {
+                wire::MagicType::HW_END => return Some((code, values)),
+                wire::MagicType::HW_VARCHAR => {
+                    stream.read_exact(&mut byte).unwrap();
+                    let mut text = vec![0; byte[0] as usize];
+                    stream.read_exact(&mut text).unwrap();
+                    values.push(wire::Value::Varchar(String::from_utf8(text).unwrap()));
+                }
+                magic => {
+                    stream.read_exact(&mut number).unwrap();
+                    let number = u32::from_le_bytes(number);
+                    values.push(match magic {
+                        wire::MagicType::HW_OBJECT => wire::Value::Object(number),
+                        wire::MagicType::HW_SEQ => wire::Value::Seq(number),
+                        _ => wire::Value::Uint(number),
+                    });
+                }
+            }
+        }
+    }
+
+    /// Write a message made of a code and raw encoded values
+    fn write(stream: &mut UnixStream, code: u8, values: &[&[u8]]) {
+        let mut message = vec![code];
+        for value in values {
+            message.extend_from_slice(value);
+        }
+        message.push(wire::MagicType::HW_END);
+        stream.write_all(&message).unwrap();
+    }
+
+    fn uint(magic: u8, value: u32) -> Vec<u8> {
+        let mut encoded = vec![magic];
+        encoded.extend_from_slice(&value.to_le_bytes());
+        encoded
+    }
+
+    fn varchar(value: &str) -> Vec<u8> {
+        let mut encoded = vec![value.len() as u8];
+        encoded.extend_from_slice(value.as_bytes());
+        encoded
+    }
 
-                    // try to disconnect properly, but dont panic if we cant
-                    client.disconnect().unwrap_or(());
+    /// Serve one client like hyprpaper would, failing applies on unknown monitors
+    fn fake_hyprpaper(listener: UnixListener) -> Requests {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut requests = Requests::default();
+
+        let (_, values) = read(&mut stream).unwrap();
+        if let Some(wire::Value::Varchar(greeting)) = values.first() {
+            requests.greeting = Some(greeting.to_owned());
+        }
+        let mut versions = vec![wire::MagicType::HW_ARRAY, wire::MagicType::HW_UINT, 1];
+        versions.extend_from_slice(&1_u32.to_le_bytes());
+        write(&mut stream, wire::Code::HW_HANDSHAKE_BEGIN, &[&versions]);
+
+        read(&mut stream).unwrap();
+        let mut protocols = vec![wire::MagicType::HW_ARRAY, wire::MagicType::HW_VARCHAR, 2];
+        protocols.extend(varchar("hyprland_other@3"));
+        protocols.extend(varchar("hyprpaper_core@2"));
+        write(
+            &mut stream,
+            wire::Code::HW_HANDSHAKE_PROTOCOLS,
+            &[&protocols],
+        );
+
+        let (_, values) = read(&mut stream).unwrap();
+        if let [_, wire::Value::Varchar(spec), wire::Value::Uint(version)] = values.as_slice() {
+            requests.bound = Some((spec.to_owned(), *version));
+        }
+        let new_object = |stream: &mut UnixStream, id: u32| {
+            write(
+                stream,
+                wire::Code::HW_NEW_OBJECT,
+                &[
+                    &uint(wire::MagicType::HW_UINT, id),
+                    &uint(wire::MagicType::HW_UINT, 0),
+                ],
+            );
+        };
+        new_object(&mut stream, 1);
 
-                    return Ok(());
+        // an unrelated event on the manager has to be skipped
+        let mut monitor = vec![wire::MagicType::HW_VARCHAR];
+        monitor.extend(varchar("DP-1"));
+        write(
+            &mut stream,
+            wire::Code::HW_GENERIC_PROTOCOL_MESSAGE,
+            &[
+                &uint(wire::MagicType::HW_OBJECT, 1),
+                &uint(wire::MagicType::HW_UINT, 0),
+                &monitor,
+            ],
+        );
+
+        let mut next = 2;
+        let mut monitors: HashMap<u32, String> = HashMap::new();
+        while let Some((_, values)) = read(&mut stream) {
+            let [
+                wire::Value::Object(object),
+                wire::Value::Uint(method),
+                arguments @ ..,
+            ] = values.as_slice()
+            else {
+                panic!("unexpected request");
+            };
+            match (*object, *method, arguments) {
+                (1, MANAGER_GET_WALLPAPER, _) => {
+                    new_object(&mut stream, next);
+                    next += 1;
+                }
+                (object, WALLPAPER_MONITOR, [wire::Value::Varchar(name), ..]) => {
+                    monitors.insert(object, name.to_owned());
                 }
-                Err(_) => {
-                    thread::sleep(Duration::from_millis(250));
+                (_, WALLPAPER_FIT_MODE, [wire::Value::Uint(fit), ..]) => {
+                    requests.fits.push(*fit);
                 }
+                (object, WALLPAPER_APPLY, _) => {
+                    let mut reply = vec![
+                        uint(wire::MagicType::HW_OBJECT, object),
+                        uint(wire::MagicType::HW_UINT, WALLPAPER_SUCCESS),
+                    ];
+                    if monitors[&object] == "BAD-1" {
+                        reply = vec![
+                            uint(wire::MagicType::HW_OBJECT, object),
+                            uint(wire::MagicType::HW_UINT, WALLPAPER_FAILED),
+                            uint(wire::MagicType::HW_UINT, 1),
+                        ];
+                    }
+                    let reply: Vec<&[u8]> = reply.iter().map(Vec::as_slice).collect();
+                    write(&mut stream, wire::Code::HW_GENERIC_PROTOCOL_MESSAGE, &reply);
+                }
+                (object, WALLPAPER_DESTROY, _) => requests.destroyed.push(object),
+                _ => {}
             }
         }
 
-        Err("hyprpaper: connection timeout reached".to_string())
+        requests
+    }
+
+    #[test]
+    fn talks_to_hyprpaper_socket() {
+        let socket_path = env::temp_dir().join(format!("rwps_hyprpaper_{}.sock", process::id()));
+        let listener = UnixListener::bind(&socket_path).unwrap();
+        let socket_path = socket_path.to_string_lossy().to_string();
+        let server = thread::spawn(move || fake_hyprpaper(listener));
+
+        let mut hyprpaper = Hyprpaper::new();
+        let first = HashMap::from([
+            ("DP-1".to_string(), "/cache/rwps_DP-1.png".to_string()),
+            (
+                "HDMI-A-1".to_string(),
+                "/cache/rwps_HDMI-A-1.png".to_string(),
+            ),
+        ]);
+        assert_eq!(
+            hyprpaper.push(&socket_path, &first, HyprpaperFit::Contain, 2),
+            Ok(())
+        );
+
+        // a rejected output is reported, the others are still applied
+        let second = HashMap::from([
+            ("BAD-1".to_string(), "/cache/rwps_BAD-1.png".to_string()),
+            ("DP-1".to_string(), "/cache/rwps_DP-1_next.png".to_string()),
+        ]);
+        assert_eq!(
+            hyprpaper.push(&socket_path, &second, HyprpaperFit::Contain, 2),
+            Err("hyprpaper: BAD-1: invalid monitor".to_string())
+        );
+
+        drop(hyprpaper);
+        let requests = server.join().unwrap();
+        fs::remove_file(&socket_path).unwrap();
+
+        assert_eq!(requests.greeting.as_deref(), Some("VAX"));
+        assert_eq!(requests.bound, Some((PROTOCOL.to_string(), 2)));
+        assert_eq!(requests.fits, vec![HyprpaperFit::Contain as u32; 4]);
+        // the failed object, then the image DP-1 showed before, then the output that is gone
+        assert_eq!(requests.destroyed, vec![4, 2, 3]);
     }
 }
@@ -1,6 +1,164 @@
+pub mod custom;
 pub mod hyprlock;
 pub mod hyprpaper;
+pub mod native;
 pub mod palette;
+pub mod sway;
 pub mod swaybg;
 pub mod swaylock;
+pub mod swww;
 pub mod wpaperd;
+
+use crate::cli::{self, Config, Locker};
+use crate::helpers::Helpers;
+use std::collections::HashMap;
+use std::os::fd::OwnedFd;
+use std::path::PathBuf;
+
+// a wallpaper setter the split images are handed to
+pub trait Backend {
+    /// Return the program the backend relies on, none if it's built in
+    fn program(&self, config: &Config) -> Option<String>;
+    /// Check if everything the backend relies on is present
+    fn is_available(&self, config: &Config) -> bool {
+        self.program(config)
+            .is_none_or(|program| Helpers::is_installed(&program))
+    }
+    /// Check if the backend still shows the images of a hash
+    fn is_current(&self, config: &Config, hash: &str) -> Result<bool, String>;
+    /// Show new wallpapers on their outputs
+    fn apply(
+        &mut self,
+        config: &Config,
+        outputs: &HashMap<String, String>,
+        hash: &str,
+    ) -> Result<(), String>;
+    /// Handle pending work between runs
+    fn dispatch(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+    /// Return an id of the connection behind poll_fd, a new connection gets a new one
+    fn connection(&self) -> Option<u64> {
+        None
+    }
+    /// Return a copy of the connection that turns readable when there is work to dispatch
+    fn poll_fd(&self) -> Option<OwnedFd> {
+        None
+    }
+    /// Reap processes of the backend, returning if any exited on their own
+    fn reap(&mut self) -> bool {
+        false
+    }
+    /// Stop whatever the backend started
+    fn shutdown(&mut self) {}
+}
+
+// a lockscreen config generated next to the split images
+pub trait LockerConfig {
+    /// Return where the config is saved
+    fn path(&self, workdir: &str) -> PathBuf;
+    /// Build and save a new config to disk
+    fn generate(&self, workdir: &str, outputs: &HashMap<String, String>) -> Result<(), String>;
+}
+
+pub struct Registry;
+impl Registry {
+    /// Return a new instance of a backend
+    pub fn backend(backend: &cli::Backend) -> Box<dyn Backend> {
+        match backend {
+            cli::Backend::Wpaperd => Box::new(wpaperd::Wpaperd::new()),
+            cli::Backend::Swaybg => Box::new(swaybg::Swaybg::new()),
+            cli::Backend::Hyprpaper => Box::new(hyprpaper::Hyprpaper::new()),
+            cli::Backend::Native => Box::new(native::Native::new()),
+            cli::Backend::Swww => Box::new(swww::Swww::new()),
+            cli::Backend::Sway => Box::new(sway::Sway),
+            cli::Backend::Custom => Box::new(custom::Custom::new()),
+        }
+    }
+    /// Return the config generator of a locker
+    pub fn locker(locker: &Locker) -> Box<dyn LockerConfig> {
+        match locker {
+            Locker::Swaylock => Box::new(swaylock::Swaylock),
+            Locker::Hyprlock => Box::new(hyprlock::Hyprlock),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    // output and image of each section in a locker config
+    type Sections = Vec<(String, String)>;
+
+    // read the sections back from a swaylock config
+    fn swaylock_sections(content: &str) -> Sections {
+        let arguments: Vec<&str> = content.split_whitespace().collect();
+        arguments
+            .chunks(2)
+            .filter_map(|pair| match pair {
+                ["-i", image] => image.split_once(':'),
+                _ => None,
+            })
+            .map(|(name, path)| (name.to_string(), path.to_string()))
+            .collect()
+    }
+
+    // read the sections back from a hyprlock config
+    fn hyprlock_sections(content: &str) -> Sections {
+        content
+            .split("background {")
+            .skip(1)
+            .filter_map(|section| {
+                let value = |key: &str| {
+                    section
+                        .lines()
+                        .find_map(|line| line.trim().strip_prefix(key))
+                        .map(|value| value.trim_start_matches([' ', '=']).to_string())
+                };
+                Some((value("monitor")?, value("path")?))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generates_a_section_per_output() {
+        let workdir = env::temp_dir().join(format!("rwps_lockers_{}", process::id()));
+        fs::create_dir_all(&workdir).unwrap();
+        let workdir = workdir.to_string_lossy().to_string();
+        let wallpapers = HashMap::from([
+            ("DP-1".to_string(), "/cache/rwps_DP-1.png".to_string()),
+            (
+                "HDMI-A-1".to_string(),
+                "/cache/rwps_HDMI-A-1.png".to_string(),
+            ),
+        ]);
+
+        let lockers = [
+            (
+                Locker::Swaylock,
+                "rwps_swaylock.conf",
+                swaylock_sections as fn(&str) -> Sections,
+            ),
+            (Locker::Hyprlock, "rwps_hyprlock.conf", hyprlock_sections),
+        ];
+        for (locker, file, sections) in lockers {
+            let generator = Registry::locker(&locker);
+            generator.generate(&workdir, &wallpapers).unwrap();
+
+            // hooks are told this path, so the config has to be right there
+            let path = generator.path(&workdir);
+            assert_eq!(path, PathBuf::from(&workdir).join(file));
+            let mut found = sections(&fs::read_to_string(&path).unwrap());
+            found.sort();
+            let mut expected: Sections = wallpapers.clone().into_iter().collect();
+            expected.sort();
+            assert_eq!(found, expected, "{}", file);
+        }
+
+        fs::remove_dir_all(&workdir).unwrap();
+    }
+}
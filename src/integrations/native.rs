@@ -0,0 +1,500 @@
+use super::Backend;
+use crate::cli::Config;
+use image::imageops::FilterType;
+use smithay_client_toolkit::reexports::client::{
+    Connection, EventQueue, QueueHandle,
+    backend::WaylandError,
+    globals::registry_queue_init,
+    protocol::{wl_output, wl_shm, wl_surface},
+};
+use smithay_client_toolkit::{
+    compositor::{CompositorHandler, CompositorState},
+    delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm,
+    output::{OutputHandler, OutputState},
+    registry::{ProvidesRegistryState, RegistryState},
+    registry_handlers,
+    shell::{
+        WaylandSurface,
+        wlr_layer::{
+            Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+            LayerSurfaceConfigure,
+        },
+    },
+    shm::{
+        Shm, ShmHandler,
+        slot::{Buffer, SlotPool},
+    },
+};
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::os::fd::OwnedFd;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// ids of all sessions so far, to tell a new connection from the previous one
+static SESSIONS: AtomicU64 = AtomicU64::new(0);
+
+// a background surface on a single output
+struct Surface {
+    layer: LayerSurface,
+    path: String,
+    size: Option<(u32, u32)>,
+    // kept until replaced, the compositor may still read from it
+    buffer: Option<Buffer>,
+    drawn: bool,
+}
+
+struct NativeState {
+    registry_state: RegistryState,
+    output_state: OutputState,
+    compositor_state: CompositorState,
+    layer_shell: LayerShell,
+    shm: Shm,
+    pool: SlotPool,
+    surfaces: HashMap<String, Surface>,
+    // the hash all surfaces were last drawn for
+    hash: Option<String>,
+    error: Option<String>,
+}
+
+impl CompositorHandler for NativeState {
+    fn scale_factor_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_factor: i32,
+    ) {
+    }
+
+    fn transform_changed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _new_transform: wl_output::Transform,
+    ) {
+    }
+
+    fn frame(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _time: u32,
+    ) {
+    }
+
+    fn surface_enter(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+
+    fn surface_leave(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _surface: &wl_surface::WlSurface,
+        _output: &wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl OutputHandler for NativeState {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+
+    fn update_output(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+
+    fn output_destroyed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _output: wl_output::WlOutput,
+    ) {
+    }
+}
+
+impl LayerShellHandler for NativeState {
+    fn closed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, layer: &LayerSurface) {
+        // the output went away, a resplit creates a new surface if it comes back
+        self.surfaces.retain(|_, surface| &surface.layer != layer);
+        self.hash = None;
+    }
+
+    fn configure(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        layer: &LayerSurface,
+        configure: LayerSurfaceConfigure,
+        _serial: u32,
+    ) {
+        let Some(name) = self
+            .surfaces
+            .iter()
+            .find(|(_, surface)| &surface.layer == layer)
+            .map(|(name, _)| name.to_owned())
+        else {
+            return;
+        };
+        if let Some(surface) = self.surfaces.get_mut(&name) {
+            let size = Some(configure.new_size);
+            surface.drawn &= surface.size == size;
+            surface.size = size;
+        }
+        if let Err(err) = self.draw(&name) {
+            self.error = Some(err);
+        }
+    }
+}
+
+impl ShmHandler for NativeState {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.shm
+    }
+}
+
+impl ProvidesRegistryState for NativeState {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    registry_handlers! {
+        OutputState,
+    }
+}
+
+delegate_compositor!(NativeState);
+delegate_output!(NativeState);
+delegate_layer!(NativeState);
+delegate_shm!(NativeState);
+delegate_registry!(NativeState);
+
+impl NativeState {
+    /// Draw the image of an output once its surface is configured
+    fn draw(&mut self, name: &str) -> Result<(), String> {
+        let Some(surface) = self.surfaces.get_mut(name) else {
+            return Ok(());
+        };
+        let Some((width, height)) = surface.size.filter(|_| !surface.drawn) else {
+            return Ok(());
+        };
+
+        // splits match the logical output size, only odd configures need scaling
+        let mut image = image::open(&surface.path)
+            .map_err(|_| format!("native: failed to open {}", surface.path))?;
+        if image.width() != width || image.height() != height {
+            image = image.resize_exact(width, height, FilterType::Lanczos3);
+        }
+        let image = image.to_rgba8();
+
+        let (buffer, canvas) = self
+            .pool
+            .create_buffer(
+                width as i32,
+                height as i32,
+                width as i32 * 4,
+                wl_shm::Format::Xrgb8888,
+            )
+            .map_err(|_| "native: failed to allocate buffer")?;
+        // shm pixels are little endian
+        for (target, pixel) in canvas.chunks_exact_mut(4).zip(image.pixels()) {
+            target.copy_from_slice(&[pixel[2], pixel[1], pixel[0], 255]);
+        }
+
+        let wl_surface = surface.layer.wl_surface();
+        wl_surface.damage_buffer(0, 0, width as i32, height as i32);
+        buffer
+            .attach_to(wl_surface)
+            .map_err(|_| "native: failed to attach buffer")?;
+        surface.layer.commit();
+        surface.buffer = Some(buffer);
+        surface.drawn = true;
+
+        Ok(())
+    }
+}
+
+// a connection owning the surfaces
+struct Session {
+    id: u64,
+    conn: Connection,
+    queue: EventQueue<NativeState>,
+    state: NativeState,
+}
+
+impl Session {
+    /// Connect and return a new session without any surfaces yet
+    fn connect() -> Result<Self, String> {
+        let conn = Connection::connect_to_env().map_err(|_| "native: failed to connect")?;
+        let (globals, queue) =
+            registry_queue_init(&conn).map_err(|_| "native: failed to init queue")?;
+        let qh = queue.handle();
+
+        let shm = Shm::bind(&globals, &qh).map_err(|_| "native: compositor lacks wl_shm")?;
+        let pool = SlotPool::new(4096, &shm).map_err(|_| "native: failed to create pool")?;
+        let mut state = NativeState {
+            registry_state: RegistryState::new(&globals),
+            output_state: OutputState::new(&globals, &qh),
+            compositor_state: CompositorState::bind(&globals, &qh)
+                .map_err(|_| "native: compositor lacks wl_compositor")?,
+            layer_shell: LayerShell::bind(&globals, &qh)
+                .map_err(|_| "native: compositor lacks wlr-layer-shell")?,
+            shm,
+            pool,
+            surfaces: HashMap::new(),
+            hash: None,
+            error: None,
+        };
+
+        let mut queue = queue;
+        queue
+            .roundtrip(&mut state)
+            .map_err(|_| "native: roundtrip failed")?;
+
+        Ok(Self {
+            id: SESSIONS.fetch_add(1, Ordering::Relaxed),
+            conn,
+            queue,
+            state,
+        })
+    }
+    /// Show the given wallpapers, only redrawing outputs whose image changed
+    fn apply(&mut self, wallpapers: &HashMap<String, String>, hash: &str) -> Result<(), String> {
+        // pick up output changes first
+        self.queue
+            .roundtrip(&mut self.state)
+            .map_err(|_| "native: roundtrip failed")?;
+
+        self.state.hash = None;
+        self.state
+            .surfaces
+            .retain(|name, _| wallpapers.contains_key(name));
+        let qh = self.queue.handle();
+        for (name, path) in wallpapers {
+            if let Some(surface) = self.state.surfaces.get_mut(name) {
+                surface.drawn &= surface.path == *path;
+                surface.path = path.to_owned();
+                continue;
+            }
+
+            let output = self
+                .state
+                .output_state
+                .outputs()
+                .find(|output| {
+                    self.state
+                        .output_state
+                        .info(output)
+                        .and_then(|info| info.name)
+                        .as_ref()
+                        == Some(name)
+                })
+                .ok_or(format!("native: output {} not found", name))?;
+
+            // cover the whole output below everything else
+            let layer = self.state.layer_shell.create_layer_surface(
+                &qh,
+                self.state.compositor_state.create_surface(&qh),
+                Layer::Background,
+                Some("rwpspread"),
+                Some(&output),
+            );
+            layer.set_anchor(Anchor::all());
+            layer.set_exclusive_zone(-1);
+            layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+            layer.set_size(0, 0);
+            layer.commit();
+
+            self.state.surfaces.insert(
+                name.to_owned(),
+                Surface {
+                    layer,
+                    path: path.to_owned(),
+                    size: None,
+                    buffer: None,
+                    drawn: false,
+                },
+            );
+        }
+
+        // new surfaces are drawn once configured, known ones right away
+        let names: Vec<String> = self.state.surfaces.keys().cloned().collect();
+        for name in names {
+            self.state.draw(&name)?;
+        }
+        self.queue
+            .roundtrip(&mut self.state)
+            .map_err(|_| "native: roundtrip failed")?;
+        if let Some(err) = self.state.error.take() {
+            return Err(err);
+        }
+        if let Some(name) = self
+            .state
+            .surfaces
+            .iter()
+            .find(|(_, surface)| !surface.drawn)
+            .map(|(name, _)| name)
+        {
+            return Err(format!("native: {} was never configured", name));
+        }
+        // surfaces drawn while handling configures are still queued
+        self.queue.flush().map_err(|_| "native: connection lost")?;
+        self.state.hash = Some(hash.to_string());
+
+        Ok(())
+    }
+    /// Handle pending events without blocking
+    fn dispatch(&mut self) -> Result<(), String> {
+        self.queue.flush().map_err(|_| "native: connection lost")?;
+        if let Some(guard) = self.queue.prepare_read() {
+            match guard.read() {
+                Ok(_) => {}
+                Err(WaylandError::Io(err)) if err.kind() == ErrorKind::WouldBlock => {}
+                Err(_) => return Err("native: connection lost".to_string()),
+            }
+        }
+        self.queue
+            .dispatch_pending(&mut self.state)
+            .map_err(|_| "native: connection lost")?;
+        if let Some(err) = self.state.error.take() {
+            return Err(err);
+        }
+
+        Ok(())
+    }
+}
+
+pub struct Native {
+    session: Option<Session>,
+}
+
+impl Native {
+    pub fn new() -> Self {
+        Self { session: None }
+    }
+}
+
+impl Backend for Native {
+    fn program(&self, _config: &Config) -> Option<String> {
+        None
+    }
+    fn is_current(&self, _config: &Config, hash: &str) -> Result<bool, String> {
+        Ok(self
+            .session
+            .as_ref()
+            .is_some_and(|session| session.state.hash.as_deref() == Some(hash)))
+    }
+    fn apply(
+        &mut self,
+        _config: &Config,
+        outputs: &HashMap<String, String>,
+        hash: &str,
+    ) -> Result<(), String> {
+        // keep the connection, it owns the surfaces
+        if self.session.is_none() {
+            self.session = Some(Session::connect()?);
+        }
+        if let Some(session) = &mut self.session {
+            session.apply(outputs, hash)?;
+        }
+
+        Ok(())
+    }
+    fn dispatch(&mut self) -> Result<(), String> {
+        let Some(session) = &mut self.session else {
+            return Ok(());
+        };
+        // reconnect on the next run
+        if let Err(err) = session.dispatch() {
+            self.session = None;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+    fn connection(&self) -> Option<u64> {
+        self.session.as_ref().map(|session| session.id)
+    }
+    fn poll_fd(&self) -> Option<OwnedFd> {
+        let session = self.session.as_ref()?;
+        session.conn.backend().poll_fd().try_clone_to_owned().ok()
+    }
+    fn shutdown(&mut self) {
+        self.session = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wayland::Wayland;
+    use image::{Rgb, RgbImage};
+    use std::env;
+    use std::fs;
+
+    // start a headless compositor first, for example
+    // WLR_BACKENDS=headless WLR_LIBINPUT_NO_DEVICES=1 sway
+    // and run with WAYLAND_DISPLAY pointing at it and --ignored
+    #[test]
+    #[ignore = "needs a headless compositor with wlr-layer-shell in $WAYLAND_DISPLAY"]
+    fn draws_on_headless_outputs() {
+        let monitors = Wayland::connect().unwrap().get_monitors().unwrap();
+        assert!(!monitors.is_empty());
+
+        let mut wallpapers: HashMap<String, String> = HashMap::new();
+        for monitor in &monitors {
+            let path = env::temp_dir().join(format!("rwps_native_{}.png", monitor.name));
+            RgbImage::from_pixel(monitor.width, monitor.height, Rgb([255, 0, 0]))
+                .save(&path)
+                .unwrap();
+            wallpapers.insert(monitor.name.to_owned(), path.to_string_lossy().to_string());
+        }
+
+        let mut session = Session::connect().unwrap();
+        session.apply(&wallpapers, "first").unwrap();
+        assert_eq!(session.state.surfaces.len(), monitors.len());
+        assert!(
+            session
+                .state
+                .surfaces
+                .values()
+                .all(|surface| surface.drawn && surface.buffer.is_some())
+        );
+
+        // unchanged images keep their surfaces, only the hash moves on
+        session.apply(&wallpapers, "second").unwrap();
+        session.dispatch().unwrap();
+        assert_eq!(session.state.hash.as_deref(), Some("second"));
+
+        // the daemon waits on the connection for configures and releases
+        let native = Native {
+            session: Some(session),
+        };
+        assert!(native.connection().is_some());
+        assert!(native.poll_fd().is_some());
+
+        for path in wallpapers.values() {
+            fs::remove_file(path).unwrap();
+        }
+    }
+}
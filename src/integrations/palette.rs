@@ -1,11 +1,11 @@
-use image::{GenericImageView, Rgba};
+use image::{DynamicImage, GenericImageView, Rgba};
 use material_colors::theme::Schemes as MaterialSchemes;
 use material_colors::{color::Argb, theme::ThemeBuilder};
 use serde::Serialize;
 use serde_json::{Map, Value, to_writer_pretty as json_to_file};
 use std::collections::HashMap;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 #[derive(Serialize)]
@@ -39,7 +39,19 @@ pub struct Palette {
 impl Palette {
     /// Generate a new color palette based on an input images
     pub fn new(image_path: &PathBuf) -> Result<Self, String> {
-        let pixels = Palette::extract_rgba_pixels(image_path)?;
+        let img = image::open(image_path).map_err(|err| err.to_string())?;
+        let pixels = Palette::extract_rgba_pixels(img)?;
+        Ok(Self {
+            path: image_path.to_string_lossy().to_string(),
+            pixels,
+            colors: Vec::with_capacity(16),
+            schemes: None,
+        })
+    }
+    /// Generate a new color palette based on in-memory image data
+    pub fn from_memory(bytes: &[u8], image_path: &Path) -> Result<Self, String> {
+        let img = image::load_from_memory(bytes).map_err(|err| err.to_string())?;
+        let pixels = Palette::extract_rgba_pixels(img)?;
         Ok(Self {
             path: image_path.to_string_lossy().to_string(),
             pixels,
@@ -63,7 +75,7 @@ impl Palette {
 
         // sort by color frequency
         let mut count_vec: Vec<(Rgba<u8>, usize)> = color_map.into_iter().collect();
-        count_vec.sort_by(|a, b| b.1.cmp(&a.1));
+        count_vec.sort_by_key(|entry| std::cmp::Reverse(entry.1));
 
         // extract most used and feed to material generator
         // only runs if we have a most frequent color
@@ -86,7 +98,6 @@ impl Palette {
         // if no suitable color is found, reshade the last one
         let mut last_color = (0, 0, 0);
         (0..16)
-            .into_iter()
             .map(|num| {
                 // find color with relative luminance calculation
                 let chosen_color: (u8, u8, u8) = count_vec
@@ -121,15 +132,12 @@ impl Palette {
             .count();
 
         // process and save to json
-        self.to_json(output_path)?;
+        self.write_json(output_path)?;
 
         Ok(())
     }
     /// Extract RGB pixels from an input image
-    fn extract_rgba_pixels(image_path: &PathBuf) -> Result<Vec<Rgba<u8>>, String> {
-        // Load the image
-        let img = image::open(image_path).map_err(|err| err.to_string())?;
-
+    fn extract_rgba_pixels(img: DynamicImage) -> Result<Vec<Rgba<u8>>, String> {
         // determine resolution and downscale divisor
         let (width, height) = img.dimensions();
         // @TODO: Might make this changeable by user in the future
@@ -156,8 +164,7 @@ impl Palette {
     fn gamma_correct(&self, input: u8) -> f64 {
         // approximate gamma correction for sRGB range
         let gamma = 2.2;
-        let linear = (input as f64 / 255.0).powf(gamma);
-        linear
+        (input as f64 / 255.0).powf(gamma)
     }
     /// Calculate the relative luminance of an input RGB value
     fn relative_luminance(&self, input: (u8, u8, u8)) -> f64 {
@@ -199,7 +206,7 @@ impl Palette {
         )
     }
     /// Generate and save a new JSON palette file to disk
-    fn to_json(self, path: &String) -> Result<(), String> {
+    fn write_json(self, path: &String) -> Result<(), String> {
         // define a map for each color scheme
         let (mut luminance_colors, mut material_dark_colors, mut material_light_colors) =
             (Map::new(), Map::new(), Map::new());
@@ -214,9 +221,7 @@ impl Palette {
 
         // extract the color schemes from the material colors generator
         if let Some(palletes) = self.schemes {
-            for (dark_color, light_color) in
-                palletes.dark.into_iter().zip(palletes.light.into_iter())
-            {
+            for (dark_color, light_color) in palletes.dark.into_iter().zip(palletes.light) {
                 material_dark_colors.insert(
                     dark_color.0.split("_").collect::<String>(),
                     Value::String((dark_color.1.to_string()).to_string()),
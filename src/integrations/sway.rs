@@ -0,0 +1,209 @@
+use super::Backend;
+use crate::cli::Config;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+// every i3-ipc message starts with this
+const MAGIC: &[u8; 6] = b"i3-ipc";
+const RUN_COMMAND: u32 = 0;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct CommandReply {
+    success: bool,
+    error: Option<String>,
+}
+
+pub struct Sway;
+impl Sway {
+    /// Return the sway ipc socket path
+    fn socket_path() -> Result<String, String> {
+        env::var("SWAYSOCK").map_err(|_| "sway: $SWAYSOCK is not set".to_string())
+    }
+    /// Set new wallpapers through the sway ipc socket
+    fn push(socket_path: &str, wallpapers: &HashMap<String, String>) -> Result<(), String> {
+        let mut stream = UnixStream::connect(socket_path)
+            .map_err(|_| format!("sway: failed to connect to {}", socket_path))?;
+        stream
+            .set_read_timeout(Some(TIMEOUT))
+            .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+            .map_err(|_| "sway: failed to configure socket")?;
+
+        // one command per output, replies come back in the same order
+        let mut names: Vec<&String> = wallpapers.keys().collect();
+        names.sort();
+        let commands: Vec<String> = names
+            .iter()
+            .map(|name| {
+                format!(
+                    "output {} bg {} fill",
+                    Sway::quote(name),
+                    Sway::quote(&wallpapers[*name])
+                )
+            })
+            .collect();
+        Sway::send(&mut stream, RUN_COMMAND, commands.join("; ").as_bytes())?;
+
+        let payload = Sway::receive(&mut stream, RUN_COMMAND)?;
+        let replies: Vec<CommandReply> =
+            serde_json::from_slice(&payload).map_err(|_| "sway: invalid reply")?;
+        if replies.len() != names.len() {
+            return Err("sway: incomplete reply".to_string());
+        }
+        let failures: Vec<String> = names
+            .iter()
+            .zip(replies)
+            .filter(|(_, reply)| !reply.success)
+            .map(|(name, reply)| {
+                format!(
+                    "{}: {}",
+                    name,
+                    reply.error.unwrap_or("unknown error".to_string())
+                )
+            })
+            .collect();
+        if !failures.is_empty() {
+            return Err(format!("sway: {}", failures.join(", ")));
+        }
+
+        Ok(())
+    }
+    /// Quote a command argument so spaces and quotes survive
+    fn quote(argument: &str) -> String {
+        format!(
+            "\"{}\"",
+            argument.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    }
+    /// Send a single message
+    fn send(stream: &mut UnixStream, message_type: u32, payload: &[u8]) -> Result<(), String> {
+        let mut message: Vec<u8> = Vec::with_capacity(14 + payload.len());
+        message.extend_from_slice(MAGIC);
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(payload);
+
+        stream
+            .write_all(&message)
+            .map_err(|_| "sway: failed to send command".to_string())
+    }
+    /// Receive a single message of the expected type and return its payload
+    fn receive(stream: &mut UnixStream, message_type: u32) -> Result<Vec<u8>, String> {
+        let mut header = [0; 14];
+        stream
+            .read_exact(&mut header)
+            .map_err(|_| "sway: failed to read reply")?;
+        if &header[..6] != MAGIC {
+            return Err("sway: invalid reply".to_string());
+        }
+        let length = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+        let reply_type = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+        if reply_type != message_type {
+            return Err("sway: unexpected reply".to_string());
+        }
+
+        let mut payload = vec![0; length as usize];
+        stream
+            .read_exact(&mut payload)
+            .map_err(|_| "sway: failed to read reply")?;
+
+        Ok(payload)
+    }
+}
+
+impl Backend for Sway {
+    fn program(&self, _config: &Config) -> Option<String> {
+        Some("sway".to_string())
+    }
+    fn is_current(&self, _config: &Config, _hash: &str) -> Result<bool, String> {
+        // sway keeps its own background clients, pushing again is cheap
+        Ok(false)
+    }
+    fn apply(
+        &mut self,
+        _config: &Config,
+        outputs: &HashMap<String, String>,
+        _hash: &str,
+    ) -> Result<(), String> {
+        Sway::push(&Sway::socket_path()?, outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    /// Answer one command on a fake sway socket, returning the command payload
+    fn fake_sway(
+        wallpapers: &HashMap<String, String>,
+        name: &str,
+        reply: &'static str,
+    ) -> (Result<(), String>, Vec<u8>) {
+        let socket_path =
+            env::temp_dir().join(format!("rwps_sway_{}_{}.sock", std::process::id(), name));
+        fs::remove_file(&socket_path).unwrap_or(());
+        let listener = UnixListener::bind(&socket_path).unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            // magic, payload length and message type, in native byte order
+            let mut header = [0; 14];
+            stream.read_exact(&mut header).unwrap();
+            assert_eq!(&header[..6], b"i3-ipc");
+            let length = u32::from_ne_bytes(header[6..10].try_into().unwrap());
+            assert_eq!(u32::from_ne_bytes(header[10..].try_into().unwrap()), 0);
+            let mut payload = vec![0; length as usize];
+            stream.read_exact(&mut payload).unwrap();
+
+            Sway::send(&mut stream, RUN_COMMAND, reply.as_bytes()).unwrap();
+            payload
+        });
+        let result = Sway::push(&socket_path.to_string_lossy(), wallpapers);
+        let payload = server.join().unwrap();
+        fs::remove_file(&socket_path).unwrap();
+
+        (result, payload)
+    }
+
+    #[test]
+    fn frames_and_quotes_commands() {
+        let wallpapers = HashMap::from([
+            ("HDMI-A-1".to_string(), "/tmp/b.png".to_string()),
+            ("DP-1".to_string(), "/tmp/my \"wall\" \\.png".to_string()),
+        ]);
+        let (result, payload) = fake_sway(
+            &wallpapers,
+            "quote",
+            r#"[{"success":true},{"success":true}]"#,
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            r#"output "DP-1" bg "/tmp/my \"wall\" \\.png" fill; output "HDMI-A-1" bg "/tmp/b.png" fill"#
+        );
+    }
+
+    #[test]
+    fn reports_failed_outputs() {
+        let wallpapers = HashMap::from([
+            ("DP-1".to_string(), "/tmp/a.png".to_string()),
+            ("HDMI-A-1".to_string(), "/tmp/b.png".to_string()),
+        ]);
+        let (result, _) = fake_sway(
+            &wallpapers,
+            "failure",
+            r#"[{"success":true},{"success":false,"error":"Unknown output"}]"#,
+        );
+
+        assert_eq!(result, Err("sway: HDMI-A-1: Unknown output".to_string()));
+    }
+}
@@ -1,17 +1,55 @@
+use super::Backend;
+use crate::cli::Config;
+use crate::helpers::Helpers;
+use crate::supervisor::Supervisor;
 use std::collections::HashMap;
 
-pub struct Swaybg;
+pub struct Swaybg {
+    supervisor: Supervisor,
+}
+
 impl Swaybg {
+    pub fn new() -> Self {
+        Self {
+            supervisor: Supervisor::new("swaybg"),
+        }
+    }
     /// Generate and return a new swaybg argument
-    pub fn new(wallpapers: &HashMap<String, String>) -> Result<Vec<&str>, String> {
+    fn arguments(wallpapers: &HashMap<String, String>) -> Vec<&str> {
         let mut arguments: Vec<&str> = Vec::new();
         for paper in wallpapers {
-            arguments.push(&"-o");
-            arguments.push(&paper.0);
-            arguments.push(&"-i");
-            arguments.push(&paper.1);
+            arguments.push("-o");
+            arguments.push(paper.0);
+            arguments.push("-i");
+            arguments.push(paper.1);
         }
 
-        Ok(arguments)
+        arguments
+    }
+}
+
+impl Backend for Swaybg {
+    fn program(&self, _config: &Config) -> Option<String> {
+        Some("swaybg".to_string())
+    }
+    fn is_current(&self, _config: &Config, _hash: &str) -> Result<bool, String> {
+        // a running instance was started with the cached images
+        Ok(self.supervisor.is_running())
+    }
+    fn apply(
+        &mut self,
+        _config: &Config,
+        outputs: &HashMap<String, String>,
+        _hash: &str,
+    ) -> Result<(), String> {
+        self.supervisor.stop()?;
+        self.supervisor
+            .spawn(Helpers::command("swaybg").args(Swaybg::arguments(outputs)))
+    }
+    fn reap(&mut self) -> bool {
+        self.supervisor.reap()
+    }
+    fn shutdown(&mut self) {
+        self.supervisor.stop().unwrap_or(());
     }
 }
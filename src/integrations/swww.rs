@@ -0,0 +1,126 @@
+use super::Backend;
+use crate::cli::Config;
+use crate::helpers::Helpers;
+use crate::supervisor::Supervisor;
+use std::collections::HashMap;
+use std::env;
+use std::os::unix::net::UnixStream;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+pub struct Swww {
+    supervisor: Supervisor,
+}
+
+impl Swww {
+    pub fn new() -> Self {
+        Self {
+            supervisor: Supervisor::new("swww-daemon"),
+        }
+    }
+    /// Return the socket paths swww-daemon may listen on, depending on its version
+    fn socket_paths() -> Result<Vec<String>, String> {
+        // find socket base with fallback
+        let socket_base: String;
+        if let Ok(xdg_dir) = env::var("XDG_RUNTIME_DIR") {
+            socket_base = xdg_dir;
+        } else if let Ok(uid) = env::var("UID") {
+            socket_base = format!("/run/user/{}", uid);
+        } else {
+            return Err("swww: no valid socket path found".to_string());
+        }
+        let display = env::var("WAYLAND_DISPLAY").unwrap_or("wayland-0".to_string());
+
+        Ok(vec![
+            format!("{}/{}-swww-daemon..sock", socket_base, display),
+            format!("{}/swww-{}.socket", socket_base, display),
+        ])
+    }
+    /// Check if swww-daemon accepts connections
+    fn is_ready() -> Result<bool, String> {
+        Ok(Swww::socket_paths()?
+            .iter()
+            .any(|socket_path| UnixStream::connect(socket_path).is_ok()))
+    }
+    /// Wait until swww-daemon accepts connections
+    fn ensure_daemon(&mut self) -> Result<(), String> {
+        if Swww::is_ready()? {
+            return Ok(());
+        }
+        self.supervisor
+            .spawn(&mut Helpers::command("swww-daemon"))?;
+
+        // block till we can connect or met retry limit
+        for _ in 0..40 {
+            if Swww::is_ready()? {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(250));
+        }
+
+        Err("swww: connection timeout reached".to_string())
+    }
+    /// Push new wallpapers to swww-daemon through its client, one output at a time
+    fn push(wallpapers: &HashMap<String, String>, config: &Config) -> Result<(), String> {
+        // the image request carries pixels already scaled to the output and passed
+        // through shared memory, in a layout that changes between swww releases,
+        // so images go through the swww client and the socket only tells readiness
+        for paper in wallpapers {
+            let mut arguments: Vec<String> = vec![
+                "img".to_string(),
+                "-o".to_string(),
+                paper.0.to_string(),
+                paper.1.to_string(),
+            ];
+            if let Some(transition) = &config.transition {
+                arguments.push(format!("--transition-type={}", transition));
+            }
+            if let Some(duration) = config.transition_duration {
+                arguments.push(format!("--transition-duration={}", duration));
+            }
+            if let Some(fps) = config.transition_fps {
+                arguments.push(format!("--transition-fps={}", fps));
+            }
+
+            let output = Helpers::command("swww")
+                .args(&arguments)
+                .stdout(process::Stdio::null())
+                .output()
+                .map_err(|_| "failed to run swww")?;
+            if !output.status.success() {
+                return Err(format!(
+                    "swww: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Backend for Swww {
+    fn program(&self, _config: &Config) -> Option<String> {
+        Some("swww".to_string())
+    }
+    fn is_current(&self, _config: &Config, _hash: &str) -> Result<bool, String> {
+        // a freshly started daemon shows nothing yet
+        Swww::is_ready()
+    }
+    fn apply(
+        &mut self,
+        config: &Config,
+        outputs: &HashMap<String, String>,
+        _hash: &str,
+    ) -> Result<(), String> {
+        self.ensure_daemon()?;
+        Swww::push(outputs, config)
+    }
+    fn reap(&mut self) -> bool {
+        self.supervisor.reap()
+    }
+    fn shutdown(&mut self) {
+        self.supervisor.stop().unwrap_or(());
+    }
+}
@@ -1,49 +1,254 @@
+use super::Backend;
+use crate::cli::{Config, WpaperdMode};
+use crate::helpers::Helpers;
+use crate::supervisor::Supervisor;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Write;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::process;
+use toml_edit::{DocumentMut, Item, Table, value};
+
+// first line of configs we wrote, followed by the hash
+const MARKER: &str = "# rwpspread:";
+// header of configs older versions generated as a whole
+const LEGACY_MARKER: &str = "# DO NOT EDIT! AUTOGENERATED CONFIG!";
+
+pub struct Wpaperd {
+    supervisor: Supervisor,
+}
 
-pub struct Wpaperd;
 impl Wpaperd {
-    /// Build and save a new wpaperd config to disk
-    pub fn new(
-        path: &String,
-        hash: &String,
+    pub fn new() -> Self {
+        Self {
+            supervisor: Supervisor::new("wpaperd"),
+        }
+    }
+    /// Return the wpaperd config location
+    fn config_path() -> Result<PathBuf, String> {
+        let config_home = match env::var("XDG_CONFIG_HOME") {
+            Ok(config_home) if !config_home.is_empty() => config_home,
+            _ => format!(
+                "{}/.config",
+                env::var("HOME").map_err(|_| "failed read $XDG_CONFIG_HOME and $HOME")?
+            ),
+        };
+
+        Ok(PathBuf::from(format!(
+            "{}/wpaperd/config.toml",
+            config_home
+        )))
+    }
+    /// Return the socket path wpaperd listens on
+    fn socket_path() -> Result<PathBuf, String> {
+        let runtime_dir =
+            env::var("XDG_RUNTIME_DIR").map_err(|_| "wpaperd: failed read $XDG_RUNTIME_DIR")?;
+
+        Ok(PathBuf::from(format!("{}/wpaperd.sock", runtime_dir)))
+    }
+    /// Check if a wpaperd is listening, no matter who started it
+    fn is_listening() -> bool {
+        Wpaperd::socket_path().is_ok_and(|path| UnixStream::connect(path).is_ok())
+    }
+    /// Read the existing config without our header lines
+    fn read(path: &Path) -> Result<String, String> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+            Err(_) => return Err("wpaperd: cant read config file".to_string()),
+        };
+
+        // the hash line of older versions comes right before their header
+        let legacy = content.lines().nth(1) == Some(LEGACY_MARKER);
+        Ok(content
+            .lines()
+            .enumerate()
+            .skip_while(|(index, line)| {
+                line.starts_with(MARKER) || *line == LEGACY_MARKER || (legacy && *index == 0)
+            })
+            .map(|(_, line)| format!("{}\n", line))
+            .collect())
+    }
+    /// Merge the new wallpapers into the existing config and save it to disk
+    fn write(
+        path: &Path,
+        hash: &str,
         wallpapers: &HashMap<String, String>,
+        mode: WpaperdMode,
     ) -> Result<(), String> {
-        // create a new config file
-        let mut config_file = File::create(path).map_err(|_| "wpaperd: cant open config file")?;
-
-        // write the hash first
-        config_file
-            .write(format!("# {}\n# DO NOT EDIT! AUTOGENERATED CONFIG!\n\n", hash).as_bytes())
-            .map_err(|_| "wpaperd: TOML write error")?;
-
-        // add default statement for image center
-        config_file
-            .write(format!("[default]\nmode = \"center\"\n\n").as_bytes())
-            .map_err(|_| "wpaperd: TOML write error")?;
-
-        // add monitor output sections
-        for paper in wallpapers {
-            config_file
-                .write(format!("[{}]\npath = \"{}\"\n\n", paper.0, paper.1).as_bytes())
-                .map_err(|_| "wpaperd: TOML write error")?;
+        // keep whatever else the user configured
+        let mut document: DocumentMut = Wpaperd::read(path)?
+            .parse()
+            .map_err(|_| "wpaperd: invalid existing config, not touching it")?;
+
+        // outputs that are gone would point to removed images
+        let stale: Vec<String> = document
+            .iter()
+            .filter(|(name, item)| {
+                !wallpapers.contains_key(*name)
+                    && item
+                        .get("path")
+                        .and_then(Item::as_str)
+                        .and_then(|path| Path::new(path).file_name())
+                        .is_some_and(|file| file.to_string_lossy().starts_with("rwps_"))
+            })
+            .map(|(name, _)| name.to_string())
+            .collect();
+        for name in stale {
+            if let Some(table) = document.get_mut(&name).and_then(Item::as_table_like_mut) {
+                table.remove("path");
+                table.remove("mode");
+                if table.is_empty() {
+                    document.remove(&name);
+                }
+            }
         }
 
-        // return
-        Ok(())
+        // add or update monitor output sections
+        let mut names: Vec<&String> = wallpapers.keys().collect();
+        names.sort();
+        for name in names {
+            let section = document
+                .entry(name)
+                .or_insert(Item::Table(Table::new()))
+                .as_table_like_mut()
+                .ok_or(format!("wpaperd: [{}] is not a table", name))?;
+            section.insert("path", value(&wallpapers[name]));
+            section.insert("mode", value(mode.to_string()));
+        }
+
+        fs::create_dir_all(path.parent().ok_or("failed to determine path parent")?)
+            .map_err(|_| "failed to create directory path")?;
+        fs::write(path, format!("{} {}\n{}", MARKER, hash, document))
+            .map_err(|_| "wpaperd: TOML write error".to_string())
     }
-    /// Check and return if any existing config is found
-    pub fn check_existing(path: &String, hash: &String) -> bool {
-        // Open the file
-        let read_file = std::fs::read_to_string(path).unwrap_or_default();
+    /// Check and return if the existing config was written for a hash
+    fn check_existing(path: &Path, hash: &str) -> bool {
+        let read_file = fs::read_to_string(path).unwrap_or_default();
 
-        // check if we find the correct hash
-        if read_file.starts_with(&format!("# {}", hash)) {
-            // hash matches, don't regenerate
-            return true;
+        read_file.lines().next() == Some(&format!("{} {}", MARKER, hash))
+    }
+    /// Run wpaperctl, returning if the daemon accepted the command
+    fn control(arguments: &[&str]) -> bool {
+        Helpers::is_installed("wpaperctl")
+            && Helpers::command("wpaperctl")
+                .args(arguments)
+                .stdout(process::Stdio::null())
+                .stderr(process::Stdio::null())
+                .status()
+                .is_ok_and(|status| status.success())
+    }
+}
+
+impl Backend for Wpaperd {
+    fn program(&self, _config: &Config) -> Option<String> {
+        Some("wpaperd".to_string())
+    }
+    fn is_current(&self, _config: &Config, hash: &str) -> Result<bool, String> {
+        // wpaperd picks up its config by itself once running
+        Ok(Wpaperd::check_existing(&Wpaperd::config_path()?, hash)
+            && (self.supervisor.is_running() || Wpaperd::is_listening()))
+    }
+    fn apply(
+        &mut self,
+        config: &Config,
+        outputs: &HashMap<String, String>,
+        hash: &str,
+    ) -> Result<(), String> {
+        Wpaperd::write(&Wpaperd::config_path()?, hash, outputs, config.wpaperd_mode)?;
+
+        // a running daemon only needs to reload, no matter who started it
+        let mut arguments: Vec<&str> = vec!["reload-wallpaper"];
+        arguments.extend(outputs.keys().map(|name| name.as_str()));
+        if Wpaperd::control(&arguments) {
+            return Ok(());
         }
+        // without wpaperctl, a running wpaperd still picks up its config on its own
+        if Wpaperd::is_listening() {
+            return Ok(());
+        }
+        self.supervisor.stop()?;
+        self.supervisor.spawn(&mut Helpers::command("wpaperd"))
+    }
+    fn reap(&mut self) -> bool {
+        self.supervisor.reap()
+    }
+    fn shutdown(&mut self) {
+        self.supervisor.stop().unwrap_or(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_into_existing_config() {
+        let workdir = env::temp_dir().join(format!("rwps_wpaperd_{}", process::id()));
+        let path = workdir.join("config.toml");
+        fs::create_dir_all(&workdir).unwrap();
+        fs::write(
+            &path,
+            "oldhash\n\
+             # DO NOT EDIT! AUTOGENERATED CONFIG!\n\
+             [default]\n\
+             duration = \"30m\"\n\
+             \n\
+             # laptop panel\n\
+             [eDP-1]\n\
+             path = \"/home/user/panel.png\"\n\
+             \n\
+             [HDMI-A-1]\n\
+             path = \"/cache/rwps_HDMI-A-1.png\"\n\
+             mode = \"center\"\n\
+             apply-shadow = true\n\
+             \n\
+             [DP-2]\n\
+             path = \"/cache/rwps_DP-2.png\"\n\
+             mode = \"center\"\n",
+        )
+        .unwrap();
+
+        let wallpapers = HashMap::from([("DP-1".to_string(), "/cache/rwps_DP-1.png".to_string())]);
+        Wpaperd::write(&path, "newhash", &wallpapers, WpaperdMode::Fit).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&workdir).unwrap();
+
+        // only our own header is left on top
+        assert!(content.starts_with("# rwpspread: newhash\n"));
+        assert!(!content.contains("oldhash"));
+        assert!(!content.contains(LEGACY_MARKER));
+        assert!(content.contains("# laptop panel"));
+
+        let document: DocumentMut = content.parse().unwrap();
+        assert_eq!(document["default"]["duration"].as_str(), Some("30m"));
+        assert_eq!(
+            document["eDP-1"]["path"].as_str(),
+            Some("/home/user/panel.png")
+        );
+        // stale outputs lose our keys, and their section once nothing else is left
+        assert!(document["HDMI-A-1"].get("path").is_none());
+        assert!(document["HDMI-A-1"].get("mode").is_none());
+        assert_eq!(document["HDMI-A-1"]["apply-shadow"].as_bool(), Some(true));
+        assert!(document.get("DP-2").is_none());
+        assert_eq!(
+            document["DP-1"]["path"].as_str(),
+            Some("/cache/rwps_DP-1.png")
+        );
+        assert_eq!(document["DP-1"]["mode"].as_str(), Some("fit"));
+    }
+
+    #[test]
+    fn recognizes_own_hash() {
+        let workdir = env::temp_dir().join(format!("rwps_wpaperd_hash_{}", process::id()));
+        let path = workdir.join("config.toml");
+        let wallpapers = HashMap::from([("DP-1".to_string(), "/cache/rwps_DP-1.png".to_string())]);
+        Wpaperd::write(&path, "somehash", &wallpapers, WpaperdMode::Center).unwrap();
 
-        false
+        assert!(Wpaperd::check_existing(&path, "somehash"));
+        assert!(!Wpaperd::check_existing(&path, "otherhash"));
+        fs::remove_dir_all(&workdir).unwrap();
     }
 }
@@ -0,0 +1,234 @@
+use crate::cli::Control;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::fd::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+// how long a client may take to send its command or read the reply
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub struct Ipc;
+impl Ipc {
+    /// Return the path of a runtime file of the current wayland session
+    pub fn runtime_path(extension: &str) -> Result<PathBuf, String> {
+        // find socket base with fallback
+        let socket_base: String;
+        if let Ok(xdg_dir) = env::var("XDG_RUNTIME_DIR") {
+            socket_base = xdg_dir;
+        } else if let Ok(uid) = env::var("UID") {
+            socket_base = format!("/run/user/{}", uid);
+        } else {
+            return Err("ipc: no valid socket path found".to_string());
+        }
+
+        // one socket per wayland display
+        let display = env::var("WAYLAND_DISPLAY").unwrap_or("wayland-0".to_string());
+
+        Ok(PathBuf::from(format!(
+            "{}/rwpspread-{}.{}",
+            socket_base,
+            display.replace('/', "_"),
+            extension
+        )))
+    }
+    /// Return the control socket path of the current wayland session
+    pub fn socket_path() -> Result<PathBuf, String> {
+        Ipc::runtime_path("sock")
+    }
+    /// Take the daemon instance lock, held until the returned file is dropped
+    pub fn lock() -> Result<File, String> {
+        let lock_path = Ipc::runtime_path("lock")?;
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .map_err(|_| format!("ipc: failed to open {}", lock_path.display()))?;
+
+        // SAFETY: flock only operates on the valid file descriptor
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let mut pid = String::new();
+            file.read_to_string(&mut pid).unwrap_or_default();
+            return Err(format!(
+                "daemon already running with pid {}, use \"rwpspread ctl\" to control it",
+                pid.trim()
+            ));
+        }
+
+        // record who holds the lock
+        file.set_len(0)
+            .and_then(|_| file.write_all(process::id().to_string().as_bytes()))
+            .map_err(|_| format!("ipc: failed to write {}", lock_path.display()))?;
+
+        Ok(file)
+    }
+    /// Bind the control socket of the current session
+    pub fn listen() -> Result<UnixListener, String> {
+        let socket_path = Ipc::socket_path()?;
+
+        // only replace sockets nobody is listening on anymore
+        if UnixStream::connect(&socket_path).is_ok() {
+            return Err(format!(
+                "ipc: {} is already in use by another daemon",
+                socket_path.display()
+            ));
+        }
+        fs::remove_file(&socket_path).unwrap_or(());
+        let listener =
+            UnixListener::bind(&socket_path).map_err(|_| "ipc: failed to bind socket")?;
+        listener
+            .set_nonblocking(true)
+            .map_err(|_| "ipc: failed to configure socket")?;
+
+        Ok(listener)
+    }
+    /// Accept all pending clients, answer their commands and return the ones following the status
+    pub fn accept<F>(listener: &UnixListener, mut handler: F) -> Vec<UnixStream>
+    where
+        F: FnMut(&Control) -> Result<String, String>,
+    {
+        let mut followers: Vec<UnixStream> = Vec::new();
+        while let Ok((stream, _)) = listener.accept() {
+            match Ipc::handle(stream, &mut handler) {
+                Ok(follower) => followers.extend(follower),
+                Err(err) => eprintln!("rwpspread: \x1B[91m{}\x1B[39m", err),
+            }
+        }
+
+        followers
+    }
+    /// Send a status line to all followers, dropping the ones that went away
+    pub fn publish(followers: &mut Vec<UnixStream>, line: &str) {
+        followers.retain_mut(|stream| stream.write_all(format!("{}\n", line).as_bytes()).is_ok());
+    }
+    /// Remove the control socket
+    pub fn cleanup() {
+        if let Ok(socket_path) = Ipc::socket_path() {
+            fs::remove_file(socket_path).unwrap_or(());
+        }
+    }
+    /// Send a control command to a running daemon and return its reply
+    pub fn send(command: &Control) -> Result<String, String> {
+        let socket_path = Ipc::socket_path()?;
+        let mut stream = UnixStream::connect(&socket_path)
+            .map_err(|_| format!("ipc: no daemon listening on {}", socket_path.display()))?;
+
+        // send the command and signal we're done writing
+        stream
+            .write_all(format!("{}\n", Ipc::encode(command)).as_bytes())
+            .map_err(|_| "ipc: failed to send command")?;
+        stream
+            .shutdown(Shutdown::Write)
+            .map_err(|_| "ipc: failed to send command")?;
+
+        // followers print every status line until the daemon stops
+        if let Control::Status { follow: true } = command {
+            return Ipc::follow(stream);
+        }
+
+        // first line is the status, rest is the payload
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .map_err(|_| "ipc: failed to read reply")?;
+        match response.split_once('\n') {
+            Some(("ok", payload)) => Ok(payload.trim_end().to_string()),
+            Some(("err", message)) => Err(message.trim_end().to_string()),
+            _ => Err("ipc: daemon sent no reply".to_string()),
+        }
+    }
+    /// Print status lines as they arrive until the daemon closes the connection
+    fn follow(stream: UnixStream) -> Result<String, String> {
+        let mut lines = BufReader::new(stream).lines();
+        match lines.next() {
+            Some(Ok(status)) if status == "ok" => {}
+            Some(Ok(status)) if status == "err" => {
+                return Err(lines
+                    .next()
+                    .and_then(Result::ok)
+                    .unwrap_or("ipc: daemon sent no reply".to_string()));
+            }
+            _ => return Err("ipc: daemon sent no reply".to_string()),
+        }
+        for line in lines {
+            let line = line.map_err(|_| "ipc: failed to read status")?;
+            println!("{}", line);
+        }
+
+        Ok(String::new())
+    }
+    /// Handle a single client connection, returning it if it follows the status
+    fn handle<F>(mut stream: UnixStream, handler: &mut F) -> Result<Option<UnixStream>, String>
+    where
+        F: FnMut(&Control) -> Result<String, String>,
+    {
+        // don't let a stuck client block the daemon
+        stream
+            .set_nonblocking(false)
+            .and_then(|_| stream.set_read_timeout(Some(CLIENT_TIMEOUT)))
+            .and_then(|_| stream.set_write_timeout(Some(CLIENT_TIMEOUT)))
+            .map_err(|_| "ipc: failed to configure client")?;
+
+        let mut line = String::new();
+        BufReader::new(&stream)
+            .read_line(&mut line)
+            .map_err(|_| "ipc: failed to read command")?;
+
+        let command = Ipc::decode(line.trim());
+        let response = match command.clone().and_then(|command| handler(&command)) {
+            Ok(payload) => format!("ok\n{}\n", payload),
+            Err(message) => format!("err\n{}\n", message),
+        };
+        stream
+            .write_all(response.as_bytes())
+            .map_err(|_| "ipc: failed to send reply")?;
+
+        // followers stay connected, the write timeout still guards against stuck ones
+        match (command, response.starts_with("ok")) {
+            (Ok(Control::Status { follow: true }), true) => Ok(Some(stream)),
+            _ => Ok(None),
+        }
+    }
+    /// Encode a control command to its wire format
+    fn encode(command: &Control) -> String {
+        match command {
+            Control::Next => "next".to_string(),
+            Control::Previous => "previous".to_string(),
+            Control::Set { path } => format!("set {}", path),
+            Control::Reload => "reload".to_string(),
+            Control::Status { follow: true } => "status --follow".to_string(),
+            Control::Status { follow: false } => "status".to_string(),
+            Control::Pause => "pause".to_string(),
+            Control::Resume => "resume".to_string(),
+            Control::Resplit { force: true } => "resplit --force".to_string(),
+            Control::Resplit { force: false } => "resplit".to_string(),
+            Control::Quit => "quit".to_string(),
+        }
+    }
+    /// Decode a control command from its wire format
+    fn decode(line: &str) -> Result<Control, String> {
+        let (name, argument) = line.split_once(' ').unwrap_or((line, ""));
+        match (name, argument.trim()) {
+            ("next", "") => Ok(Control::Next),
+            ("previous", "") => Ok(Control::Previous),
+            ("set", path) if !path.is_empty() => Ok(Control::Set {
+                path: path.to_string(),
+            }),
+            ("reload", "") => Ok(Control::Reload),
+            ("status", "--follow") => Ok(Control::Status { follow: true }),
+            ("status", "") => Ok(Control::Status { follow: false }),
+            ("pause", "") => Ok(Control::Pause),
+            ("resume", "") => Ok(Control::Resume),
+            ("resplit", "--force") => Ok(Control::Resplit { force: true }),
+            ("resplit", "") => Ok(Control::Resplit { force: false }),
+            ("quit", "") => Ok(Control::Quit),
+            _ => Err(format!("\"{}\": unknown command", line)),
+        }
+    }
+}
@@ -1,7 +1,6 @@
 use crate::helpers::Helpers;
 use crate::wayland::Monitor;
 use std::collections::HashMap;
-use std::i32;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct LayoutMonitor {
@@ -99,18 +98,14 @@ impl Layout {
             layout_monitors.push(LayoutMonitor::from_monitor(monitor));
         }
 
-        let all_same_resolution = layout_monitors
-            .iter()
-            .map(|this| {
-                let result = layout_monitors.iter().all(|monitor| {
-                    (monitor.initial_width == this.initial_width
-                        && monitor.initial_height == this.initial_height)
-                        || (monitor.initial_height == this.initial_width
-                            && monitor.initial_width == this.initial_height)
-                });
-                result
+        let all_same_resolution = layout_monitors.iter().all(|this| {
+            layout_monitors.iter().all(|monitor| {
+                (monitor.initial_width == this.initial_width
+                    && monitor.initial_height == this.initial_height)
+                    || (monitor.initial_height == this.initial_width
+                        && monitor.initial_width == this.initial_height)
             })
-            .all(|result| result == true);
+        });
 
         Self {
             monitors: layout_monitors,
@@ -138,14 +133,14 @@ impl Layout {
             .map(|(monitor, (_, &diagonal))| monitor.ppi(diagonal))
             .max()
         {
-            return ppi_max.to_owned();
+            ppi_max.to_owned()
         } else {
-            return 0;
+            0
         }
     }
     /// Compensate for different ppi values of monitors by scaling them dynamically
     pub fn compensate_ppi(&mut self, diagonals: &HashMap<String, u32>) {
-        let max_ppi = self.calculate_max_ppi(&diagonals);
+        let max_ppi = self.calculate_max_ppi(diagonals);
 
         for (r, (_, &d)) in self.monitors.iter_mut().zip(diagonals) {
             r.ppi_scale(d, max_ppi);
@@ -1,105 +1,110 @@
 mod cli;
+mod daemon;
 mod helpers;
 mod integrations;
+mod ipc;
 mod layout;
+mod notify;
+mod playlist;
+mod schedule;
+mod supervisor;
 mod watch;
 mod wayland;
 mod worker;
 
-use cli::Config;
-use crossbeam_channel::{bounded, select};
-use helpers::Helpers;
+use cli::{Config, Control, Mode};
+use daemon::Daemon;
+use integrations::Registry;
+use ipc::Ipc;
+use notify::Notify;
+use schedule::Schedule;
+use std::fs;
 use std::process;
-use watch::Watcher;
 use wayland::Wayland;
-use worker::Worker;
+use worker::{Trigger, Worker};
 
 fn run() -> Result<String, String> {
+    match Config::mode() {
+        // forward control commands to a running daemon
+        Some(Mode::Ctl { command }) => {
+            return match command {
+                // the daemon does not share our working directory
+                Control::Set { path } => Ipc::send(&Control::Set {
+                    path: fs::canonicalize(&path)
+                        .map_err(|_| format!("\"{}\": invalid path", path))?
+                        .to_string_lossy()
+                        .to_string(),
+                }),
+                command => Ipc::send(&command),
+            };
+        }
+        Some(Mode::Unit) => return Notify::unit(),
+        None => {}
+    }
+
     // check for backends if applicable
     if let Some(config) = Config::new()? {
+        // only report on the configured schedule
+        if config.info {
+            return info(Some(&config));
+        }
+
         // check for backends if applicable
-        if let Some(backend) = &config.backend {
-            if !Helpers::is_installed(&backend.to_string()) {
-                return Err(format!("{} is not installed", &backend.to_string()));
+        if let Some(backend) = config.backend.as_ref().map(Registry::backend) {
+            if !backend.is_available(&config) {
+                return Err(format!(
+                    "{} is not installed",
+                    backend.program(&config).unwrap_or_default()
+                ));
             }
         }
 
         if config.daemon {
-            // run worker initially
-            Worker::new().run(&config, Wayland::connect()?.get_monitors()?)?;
-
-            if config.watch {
-                // create two channels for threads
-                let (tx_monitors, rx_monitors) = bounded::<bool>(1);
-                let (tx_file, rx_file) = bounded::<bool>(1);
-
-                // start both operations seperately
-                let mut monitors_handle =
-                    Watcher::monitors(Wayland::connect()?, tx_monitors.clone())?;
-                let mut file_handle =
-                    Watcher::file(config.raw_input_path.clone(), tx_file.clone())?;
-
-                loop {
-                    // watch for thread channel events, resplit and restart thread
-                    select! {
-                        recv(rx_monitors) -> _ => {
-                            monitors_handle.join().map_err(|_| "thread: rwp_monitors panicked")?;
-                            if let Some(config) = Config::new()? {
-                                Worker::new().run(&config, Wayland::connect()?.get_monitors()?)?;
-                            }
-                            monitors_handle = Watcher::monitors(Wayland::connect()?, tx_monitors.clone())?;
-                        }
-                        recv(rx_file) -> _ => {
-                            file_handle.join().map_err(|_| "thread: rwp_file panicked")?;
-                            if let Some(config) = Config::new()? {
-                                Worker::new().run(&config, Wayland::connect()?.get_monitors()?)?;
-                            }
-                            file_handle = Watcher::file(config.raw_input_path.clone(), tx_file.clone())?;
-                        }
-                    }
-                }
-            } else {
-                let (tx_monitors, rx_monitors) = bounded::<bool>(1);
-
-                let mut monitors_handle =
-                    Watcher::monitors(Wayland::connect()?, tx_monitors.clone())?;
-
-                loop {
-                    if let Ok(_) = rx_monitors.recv() {
-                        monitors_handle
-                            .join()
-                            .map_err(|_| "thread: rwp_monitors panicked")?;
-                        if let Some(config) = Config::new()? {
-                            Worker::new().run(&config, Wayland::connect()?.get_monitors()?)?;
-                        }
-                        monitors_handle =
-                            Watcher::monitors(Wayland::connect()?, tx_monitors.clone())?;
-                    }
-                }
-            }
+            // keep running in an event loop
+            Daemon::run(config)?;
         } else {
             // run worker once
-            Worker::new().run(&config, Wayland::connect()?.get_monitors()?)?;
+            Worker::new().run(
+                &config,
+                Wayland::connect()?.get_monitors()?,
+                Trigger::Startup,
+            )?;
         }
     } else {
         // since no runtime config was found, return info
-        let mut result = String::new();
-        let monitors = Wayland::connect()?.get_monitors()?;
-        for (x, mon) in monitors.iter().enumerate() {
-            result.push_str(&format!(
-                "\x1B[1m\x1B[4m{}:\x1B[0m \x1B[3m{}x{}\x1B[0m at \x1B[3m{}:{}\x1B[0m",
-                mon.name, mon.width, mon.height, mon.x, mon.y
-            ));
-            if x + 1 != monitors.len() {
-                result.push_str("\n");
-            }
-        }
-        return Ok(result);
+        return info(None);
     }
 
     Ok("".to_string())
 }
 
+/// Collect and return detectable information
+fn info(config: Option<&Config>) -> Result<String, String> {
+    let mut result = String::new();
+    let monitors = Wayland::connect()?.get_monitors()?;
+    for (x, mon) in monitors.iter().enumerate() {
+        result.push_str(&format!(
+            "\x1B[1m\x1B[4m{}:\x1B[0m \x1B[3m{}x{}\x1B[0m at \x1B[3m{}:{}\x1B[0m",
+            mon.name, mon.width, mon.height, mon.x, mon.y
+        ));
+        if x + 1 != monitors.len() {
+            result.push('\n');
+        }
+    }
+
+    // report the currently active schedule slot
+    if let Some(schedule) = config.and_then(Schedule::new) {
+        let (slot, entry) = schedule.active();
+        result.push_str(&format!(
+            "\n\x1B[1m\x1B[4mschedule:\x1B[0m \x1B[3m{}\x1B[0m using \x1B[3m{}\x1B[0m",
+            slot,
+            entry.input_path.display()
+        ));
+    }
+
+    Ok(result)
+}
+
 fn main() {
     match run() {
         Ok(ok) => {
@@ -107,7 +112,7 @@ fn main() {
             process::exit(0);
         }
         Err(err) => {
-            eprintln!("{}: \x1B[91m{}\x1B[39m", "rwpspread", err);
+            eprintln!("rwpspread: \x1B[91m{}\x1B[39m", err);
             process::exit(1);
         }
     }
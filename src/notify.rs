@@ -0,0 +1,71 @@
+use std::env;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::process;
+use std::time::Duration;
+
+pub struct Notify;
+impl Notify {
+    /// Send a state update to the service manager if we were started by one
+    pub fn send(state: &str) {
+        let Ok(socket_path) = env::var("NOTIFY_SOCKET") else {
+            return;
+        };
+
+        // names starting with @ live in the abstract namespace
+        let address = match socket_path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name),
+            None => SocketAddr::from_pathname(&socket_path),
+        };
+        let sent = address
+            .and_then(|address| UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address));
+        if sent.is_err() {
+            eprintln!(
+                "rwpspread: \x1B[91mnotify: failed to reach {}\x1B[39m",
+                socket_path
+            );
+        }
+    }
+    /// Return how often the service manager expects a keep-alive ping, if at all
+    pub fn watchdog() -> Option<Duration> {
+        // the watchdog may be meant for another process
+        if let Ok(pid) = env::var("WATCHDOG_PID") {
+            if pid.parse::<u32>().ok() != Some(process::id()) {
+                return None;
+            }
+        }
+        let timeout = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+        if timeout == 0 {
+            return None;
+        }
+
+        // ping twice per timeout so a late wakeup doesn't count as a hang
+        Some(Duration::from_micros(timeout / 2))
+    }
+    /// Return a systemd user unit running the daemon
+    pub fn unit() -> Result<String, String> {
+        let executable = env::current_exe().map_err(|_| "failed to find own executable")?;
+
+        Ok(format!(
+            "\
+[Unit]
+Description=Multi-Monitor Wallpaper Utility
+Documentation=man:rwpspread(1)
+PartOf=graphical-session.target
+After=graphical-session.target
+Requisite=graphical-session.target
+
+[Service]
+Type=notify
+# options are read from $XDG_CONFIG_HOME/rwpspread/config.toml
+ExecStart={} --daemon
+ExecReload=kill -HUP $MAINPID
+Restart=on-failure
+WatchdogSec=60
+
+[Install]
+WantedBy=graphical-session.target",
+            executable.display()
+        ))
+    }
+}
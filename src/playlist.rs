@@ -0,0 +1,136 @@
+use crate::cli::Alignment;
+use crate::helpers::Helpers;
+use glob::glob;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// fit enumerator
+#[derive(Clone, Copy, Serialize, PartialEq)]
+pub enum FitMode {
+    Fill,    // Scale to cover, crop the overflow
+    Fit,     // Scale to contain, pad the rest
+    Stretch, // Scale to exact size
+}
+
+#[derive(Serialize, Clone)]
+pub struct PlaylistItem {
+    pub path: PathBuf,
+    pub align: Option<Alignment>,
+    pub focus: Option<(f32, f32)>,
+    pub fit: Option<FitMode>,
+    pub duration: Option<Duration>,
+}
+
+pub struct Playlist {
+    pub items: Vec<PlaylistItem>,
+}
+
+impl Playlist {
+    /// Load and parse a playlist file from disk
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|_| format!("\"{}\": failed to read playlist", path.display()))?;
+        let base = path.parent().unwrap_or(Path::new("/"));
+
+        let mut items: Vec<PlaylistItem> = Vec::new();
+        for line in content.lines().map(str::trim) {
+            // skip empty lines and comments
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            // options are trailing key=value pairs, everything before is the path
+            let mut template = PlaylistItem {
+                path: PathBuf::new(),
+                align: None,
+                focus: None,
+                fit: None,
+                duration: None,
+            };
+            let mut parts: Vec<&str> = line.split_whitespace().collect();
+            while parts.len() > 1 {
+                let Some((key, value)) = parts[parts.len() - 1].split_once('=') else {
+                    break;
+                };
+                let invalid = format!("\"{}\": invalid playlist option", line);
+                match key {
+                    "align" => {
+                        template.align = Some(
+                            clap::ValueEnum::from_str(value, true).map_err(|_| invalid.clone())?,
+                        );
+                    }
+                    "focus" => {
+                        let (x, y) = value.split_once(',').ok_or(invalid.clone())?;
+                        match (x.parse::<f32>(), y.parse::<f32>()) {
+                            (Ok(x), Ok(y))
+                                if (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y) =>
+                            {
+                                template.focus = Some((x, y));
+                            }
+                            _ => return Err(invalid),
+                        }
+                    }
+                    "fit" => {
+                        template.fit = Some(match value {
+                            "fill" => FitMode::Fill,
+                            "fit" => FitMode::Fit,
+                            "stretch" => FitMode::Stretch,
+                            _ => return Err(invalid),
+                        });
+                    }
+                    "duration" => {
+                        template.duration = Some(Helpers::parse_duration(value).ok_or(invalid)?);
+                    }
+                    _ => break,
+                }
+                parts.pop();
+            }
+
+            // relative entries are relative to the playlist itself
+            let entry = base.join(parts.join(" "));
+            for path in Playlist::expand(&entry)? {
+                items.push(PlaylistItem {
+                    path,
+                    ..template.clone()
+                });
+            }
+        }
+
+        if items.is_empty() {
+            return Err(format!("\"{}\": playlist has no images", path.display()));
+        }
+
+        Ok(Self { items })
+    }
+    /// Expand a playlist entry to all the images it refers to
+    fn expand(entry: &Path) -> Result<Vec<PathBuf>, String> {
+        // plain files are taken as is
+        if entry.is_file() {
+            return Ok(vec![entry.to_owned()]);
+        }
+
+        // directories are expanded to their images
+        let pattern = if entry.is_dir() {
+            entry.join("*").display().to_string()
+        } else {
+            entry.display().to_string()
+        };
+
+        let mut paths: Vec<PathBuf> = glob(&pattern)
+            .map_err(|_| format!("\"{}\": invalid playlist glob", entry.display()))?
+            .filter_map(Result::ok)
+            .filter(|path| {
+                path.is_file()
+                    && matches!(
+                        path.extension().and_then(|ext| ext.to_str()),
+                        Some("png" | "jpg" | "jpeg")
+                    )
+            })
+            .collect();
+        paths.sort();
+
+        Ok(paths)
+    }
+}
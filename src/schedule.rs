@@ -0,0 +1,146 @@
+use crate::cli::{Config, ScheduleEntry, Slot};
+use std::f64::consts::PI;
+use std::time::Duration;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+pub struct Schedule<'a> {
+    entries: &'a [ScheduleEntry],
+    starts: Vec<(Slot, u32)>,
+    now: u32,
+}
+
+impl<'a> Schedule<'a> {
+    /// Build the schedule for the current day if the config defines one
+    pub fn new(config: &'a Config) -> Option<Self> {
+        if config.schedule.is_empty() {
+            return None;
+        }
+
+        let (now, day_of_year, utc_offset) = Schedule::local_time();
+
+        // fixed defaults, overwritten by sun times or user defined times
+        let mut starts: Vec<(Slot, u32)> = vec![
+            (Slot::Morning, 6 * 60),
+            (Slot::Day, 10 * 60),
+            (Slot::Evening, 18 * 60),
+            (Slot::Night, 22 * 60),
+        ];
+        if let Some((latitude, longitude)) = config.location {
+            if let Some((sunrise, sunset)) =
+                Schedule::sun_times(latitude, longitude, day_of_year, utc_offset)
+            {
+                // day and evening are two hours apart from sunrise and sunset
+                // but never cross solar noon in between
+                let noon = (sunrise + sunset) / 2;
+                starts = vec![
+                    (Slot::Morning, sunrise),
+                    (Slot::Day, (sunrise + 120).min(noon)),
+                    (Slot::Evening, (sunset.saturating_sub(120)).max(noon)),
+                    (Slot::Night, sunset),
+                ];
+            }
+        }
+        for (slot, minutes) in &config.schedule_times {
+            for start in starts.iter_mut().filter(|start| start.0 == *slot) {
+                start.1 = *minutes;
+            }
+        }
+        starts.sort_by_key(|start| start.1);
+
+        Some(Self {
+            entries: &config.schedule,
+            starts,
+            now: now / 60,
+        })
+    }
+    /// Return the currently active slot and its scheduled input
+    pub fn active(&self) -> (Slot, &'a ScheduleEntry) {
+        // the latest start before now is active, wrapping around midnight
+        let current = self
+            .starts
+            .iter()
+            .rposition(|start| start.1 <= self.now)
+            .unwrap_or(self.starts.len() - 1);
+
+        // slots without an input keep the one of the slot before them
+        for offset in 0..self.starts.len() {
+            let index = (current + self.starts.len() - offset) % self.starts.len();
+            if let Some(entry) = self
+                .entries
+                .iter()
+                .rev()
+                .find(|entry| entry.slot == self.starts[index].0)
+            {
+                return (self.starts[current].0, entry);
+            }
+        }
+
+        // config guarantees at least one entry
+        (self.starts[current].0, &self.entries[0])
+    }
+    /// Return the time left until the next slot starts
+    pub fn next_change(&self) -> Duration {
+        let (now, _, _) = Schedule::local_time();
+        let next = self
+            .starts
+            .iter()
+            .map(|start| start.1 * 60)
+            .find(|start| *start > now)
+            .unwrap_or(self.starts[0].1 * 60 + MINUTES_PER_DAY * 60);
+
+        Duration::from_secs((next - now) as u64)
+    }
+    /// Return the local seconds since midnight, day of year and utc offset in minutes
+    fn local_time() -> (u32, u32, i64) {
+        // SAFETY: localtime_r only writes to the provided tm struct
+        unsafe {
+            let now = libc::time(std::ptr::null_mut());
+            let mut tm: libc::tm = std::mem::zeroed();
+            libc::localtime_r(&now, &mut tm);
+
+            (
+                (tm.tm_hour * 3600 + tm.tm_min * 60 + tm.tm_sec) as u32,
+                tm.tm_yday as u32 + 1,
+                tm.tm_gmtoff / 60,
+            )
+        }
+    }
+    /// Calculate and return local sunrise and sunset in minutes since midnight
+    fn sun_times(
+        latitude: f64,
+        longitude: f64,
+        day_of_year: u32,
+        utc_offset: i64,
+    ) -> Option<(u32, u32)> {
+        // https://gml.noaa.gov/grad/solcalc/solareqns.PDF
+        let gamma = 2.0 * PI / 365.0 * (day_of_year as f64 - 1.0);
+        let equation_of_time = 229.18
+            * (0.000075 + 0.001868 * gamma.cos()
+                - 0.032077 * gamma.sin()
+                - 0.014615 * (2.0 * gamma).cos()
+                - 0.040849 * (2.0 * gamma).sin());
+        let declination = 0.006918 - 0.399912 * gamma.cos() + 0.070257 * gamma.sin()
+            - 0.006758 * (2.0 * gamma).cos()
+            + 0.000907 * (2.0 * gamma).sin()
+            - 0.002697 * (3.0 * gamma).cos()
+            + 0.00148 * (3.0 * gamma).sin();
+
+        // hour angle of the sun at the horizon, polar days and nights have none
+        let latitude = latitude.to_radians();
+        let cos_hour_angle = 90.833_f64.to_radians().cos() / (latitude.cos() * declination.cos())
+            - latitude.tan() * declination.tan();
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return None;
+        }
+        let hour_angle = cos_hour_angle.acos().to_degrees();
+
+        // convert from utc to local minutes since midnight
+        let to_local =
+            |utc: f64| (utc.round() as i64 + utc_offset).rem_euclid(MINUTES_PER_DAY as i64) as u32;
+        let sunrise = to_local(720.0 - 4.0 * (longitude + hour_angle) - equation_of_time);
+        let sunset = to_local(720.0 - 4.0 * (longitude - hour_angle) - equation_of_time);
+
+        Some((sunrise, sunset))
+    }
+}
@@ -0,0 +1,137 @@
+use crate::ipc::Ipc;
+use std::fs;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// time a stopped instance gets to exit before it is killed
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+const STOP_POLL: Duration = Duration::from_millis(10);
+
+// instances of a program we started, earlier runs are found through a pid file
+pub struct Supervisor {
+    name: String,
+    children: Vec<Child>,
+}
+
+impl Supervisor {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            children: Vec::new(),
+        }
+    }
+    /// Return where the started instances are recorded
+    fn pid_path(&self) -> Result<PathBuf, String> {
+        Ipc::runtime_path(&format!("{}.pid", self.name))
+    }
+    /// Return the start time of a live process, together with the pid it identifies it
+    fn start_time(pid: i32) -> Option<u64> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        // the process name may contain anything, count fields after it
+        let fields: Vec<&str> = stat.rsplit_once(')')?.1.split_whitespace().collect();
+        if fields.first() == Some(&"Z") {
+            return None;
+        }
+        fields.get(19)?.parse().ok()
+    }
+    /// Return the pids of our running instances, each leads its own process group
+    fn instances(&self) -> Vec<i32> {
+        let Some(content) = self
+            .pid_path()
+            .ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+        else {
+            return Vec::new();
+        };
+
+        // pids may have been reused since, the start time tells them apart
+        content
+            .lines()
+            .filter_map(|line| {
+                let (pid, start_time) = line.split_once(' ')?;
+                let pid = pid.parse::<i32>().ok()?;
+                (Supervisor::start_time(pid)? == start_time.parse::<u64>().ok()?).then_some(pid)
+            })
+            .collect()
+    }
+    /// Check if any of our instances is running
+    pub fn is_running(&self) -> bool {
+        !self.instances().is_empty()
+    }
+    /// Start another instance of a command from Helpers::command and record it
+    pub fn spawn(&mut self, command: &mut Command) -> Result<(), String> {
+        // an own process group lets us stop the instance with everything it started
+        let child = command
+            .process_group(0)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|_| format!("failed to spawn: {}", self.name))?;
+
+        let mut records: String = self
+            .instances()
+            .into_iter()
+            .filter_map(|pid| Some(format!("{} {}\n", pid, Supervisor::start_time(pid)?)))
+            .collect();
+        if let Some(start_time) = Supervisor::start_time(child.id() as i32) {
+            records += &format!("{} {}\n", child.id(), start_time);
+        }
+        self.children.push(child);
+        fs::write(self.pid_path()?, records)
+            .map_err(|_| format!("{}: failed to write pid file", self.name))?;
+
+        Ok(())
+    }
+    /// Stop our instances, leaving those started by anybody else alone
+    pub fn stop(&mut self) -> Result<(), String> {
+        let instances = self.instances();
+        for pid in &instances {
+            // SAFETY: plain syscall on a process group we created
+            unsafe { libc::kill(-pid, libc::SIGTERM) };
+        }
+
+        // own children only disappear once reaped
+        let deadline = Instant::now() + STOP_TIMEOUT;
+        loop {
+            self.reap();
+            let remaining: Vec<&i32> = instances
+                .iter()
+                .filter(|pid| Supervisor::start_time(**pid).is_some())
+                .collect();
+            if remaining.is_empty() {
+                break;
+            }
+            if Instant::now() >= deadline {
+                for pid in remaining {
+                    // SAFETY: plain syscall on a process group we created
+                    unsafe { libc::kill(-pid, libc::SIGKILL) };
+                }
+                break;
+            }
+            thread::sleep(STOP_POLL);
+        }
+        for mut child in self.children.drain(..) {
+            _ = child.kill();
+            _ = child.wait();
+        }
+
+        match fs::remove_file(self.pid_path()?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("{}: failed to remove pid file", self.name))
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Reap instances that exited on their own, returning if there were any
+    pub fn reap(&mut self) -> bool {
+        let count = self.children.len();
+        self.children
+            .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+
+        self.children.len() != count
+    }
+}
@@ -1,79 +1,254 @@
-use crate::Wayland;
-use crossbeam_channel::Sender;
-use inotify::{Inotify, WatchMask};
-use std::path::PathBuf;
-use std::thread;
-use std::thread::JoinHandle;
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::mem;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+// signals handled by the daemon
+const SIGNALS: [i32; 6] = [
+    libc::SIGHUP,
+    libc::SIGUSR1,
+    libc::SIGUSR2,
+    libc::SIGTERM,
+    libc::SIGINT,
+    libc::SIGCHLD,
+];
+// same limit the kernel uses when resolving paths
+const MAX_SYMLINKS: usize = 40;
 
 pub struct Watcher;
 impl Watcher {
-    /// Watch for monitor events of a specific Wayland connection
-    pub fn monitors(mut wayland: Wayland, tx: Sender<bool>) -> Result<JoinHandle<()>, String> {
-        let thread_handle = thread::Builder::new()
-            .name("rwp_monitors".to_string())
-            .spawn(move || match wayland.refresh() {
-                Ok(resplit) => {
-                    if resplit {
-                        if let Err(err) = tx.send(true) {
-                            eprintln!("{}: \x1B[91m{}\x1B[39m", "rwpspread", err);
+    /// Block the handled signals and return a file descriptor receiving them
+    pub fn signals() -> Result<OwnedFd, String> {
+        // SAFETY: the signal set is initialized before use and the new
+        // file descriptor is owned by us
+        unsafe {
+            let mut set: libc::sigset_t = mem::zeroed();
+            libc::sigemptyset(&mut set);
+            for signal in SIGNALS {
+                libc::sigaddset(&mut set, signal);
+            }
+
+            // threads started later inherit the mask, so nobody else gets them
+            if libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut()) != 0 {
+                return Err("signals: failed to block signals".to_string());
+            }
+            let fd = libc::signalfd(-1, &set, libc::SFD_NONBLOCK | libc::SFD_CLOEXEC);
+            if fd < 0 {
+                return Err("signals: failed to create signalfd".to_string());
+            }
+
+            Ok(OwnedFd::from_raw_fd(fd))
+        }
+    }
+    /// Read and return all pending signals
+    pub fn read_signals(signal_fd: &OwnedFd) -> Vec<i32> {
+        let mut signals: Vec<i32> = Vec::new();
+        let size = mem::size_of::<libc::signalfd_siginfo>();
+        loop {
+            // SAFETY: info is large enough for exactly one signal
+            let mut info: libc::signalfd_siginfo = unsafe { mem::zeroed() };
+            if unsafe {
+                libc::read(
+                    signal_fd.as_raw_fd(),
+                    (&mut info as *mut libc::signalfd_siginfo).cast(),
+                    size,
+                )
+            } != size as isize
+            {
+                break;
+            }
+            signals.push(info.ssi_signo as i32);
+        }
+
+        signals
+    }
+    /// Create an inotify instance watching specific paths
+    pub fn file(paths: &[PathBuf]) -> Result<FileWatch, String> {
+        let mut file_watch = FileWatch {
+            inotify: Inotify::init().map_err(|_| "inotify: failed to initialize")?,
+            paths: paths.to_vec(),
+            targets: HashMap::new(),
+        };
+        file_watch.rewatch();
+        if file_watch.targets.is_empty() {
+            return Err("inotify: failed to add watch".to_string());
+        }
+
+        Ok(file_watch)
+    }
+}
+
+// file system change enumerator
+pub enum FileChange {
+    Added(PathBuf),   // An image was written to or moved into a directory
+    Removed(PathBuf), // An image was deleted or moved out of a directory
+    Changed,          // A watched path itself changed
+}
+
+// what a single watch reports on
+struct Target {
+    path: PathBuf,
+    // entries of interest in a parent, none for a watched path itself
+    names: Option<Vec<OsString>>,
+}
+
+pub struct FileWatch {
+    inotify: Inotify,
+    paths: Vec<PathBuf>,
+    targets: HashMap<WatchDescriptor, Target>,
+}
+
+impl AsFd for FileWatch {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inotify.as_fd()
+    }
+}
+
+impl FileWatch {
+    /// Consume pending file system events and return the relevant changes
+    pub fn changes(&mut self) -> Result<Vec<FileChange>, String> {
+        let mut buffer = [0; 4096];
+        let mut changes: Vec<FileChange> = Vec::new();
+        loop {
+            match self.inotify.read_events(&mut buffer) {
+                Ok(events) => {
+                    let mut count = 0;
+                    for event in events {
+                        count += 1;
+                        if let Some(target) = self.targets.get(&event.wd) {
+                            changes.extend(FileWatch::classify(target, event.mask, event.name));
                         }
                     }
+                    if count == 0 {
+                        break;
+                    }
                 }
-                Err(err) => {
-                    eprintln!("{}: \x1B[91m{}\x1B[39m", "rwpspread", err);
-                }
-            })
-            .map_err(|_| "thread: failed to start rwp_monitors")?;
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(_) => return Err("inotify: failed to read events".to_string()),
+            }
+        }
 
-        return Ok(thread_handle);
+        // links may point somewhere else and replaced files lose their watch
+        if changes
+            .iter()
+            .any(|change| matches!(change, FileChange::Changed))
+        {
+            self.rewatch();
+        }
+
+        Ok(changes)
     }
-    /// Watch for file system events of a specific path
-    pub fn file(path: PathBuf, tx: Sender<bool>) -> Result<JoinHandle<()>, String> {
-        let thread_handle = thread::Builder::new()
-            .name("rwp_file".to_string())
-            .spawn(move || match Watcher::inotify_watch_path(&path) {
-                Ok(resplit) => {
-                    if resplit {
-                        if let Err(err) = tx.send(true) {
-                            eprintln!("{}: \x1B[91m{}\x1B[39m", "rwpspread", err);
-                        }
-                    }
+    /// Return the change a single event stands for, if any
+    fn classify(target: &Target, mask: EventMask, name: Option<&OsStr>) -> Option<FileChange> {
+        // removed watches report back, that's not a change
+        if mask.contains(EventMask::IGNORED) {
+            return None;
+        }
+
+        match (&target.names, name) {
+            // the entry of a watched path in its parent
+            (Some(names), Some(name)) if names.iter().any(|entry| entry == name) => {
+                // regular files are only done once written, links right away
+                if mask.contains(EventMask::CREATE)
+                    && !fs::symlink_metadata(target.path.join(name))
+                        .is_ok_and(|metadata| metadata.is_symlink())
+                {
+                    return None;
+                }
+                Some(FileChange::Changed)
+            }
+            (Some(_), _) => None,
+            // an entry of a watched directory, only images count
+            (None, Some(name)) => {
+                let path = target.path.join(name);
+                if !matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("png" | "jpg" | "jpeg")
+                ) {
+                    return None;
                 }
-                Err(err) => {
-                    eprintln!("{}: \x1B[91m{}\x1B[39m", "rwpspread", err);
+                if mask.intersects(EventMask::CLOSE_WRITE | EventMask::MOVED_TO) {
+                    Some(FileChange::Added(path))
+                } else if mask.intersects(EventMask::DELETE | EventMask::MOVED_FROM) {
+                    Some(FileChange::Removed(path))
+                } else {
+                    None
                 }
-            })
-            .map_err(|_| "thread: failed to start rwp_file")?;
+            }
+            // a watched path itself
+            (None, None) => Some(FileChange::Changed),
+        }
+    }
+    /// Replace all watches with the ones for the current state of the paths
+    fn rewatch(&mut self) {
+        for wd in self.targets.drain().map(|target| target.0) {
+            self.inotify.watches().remove(wd).unwrap_or(());
+        }
 
-        return Ok(thread_handle);
+        for path in self.paths.clone() {
+            for (target, name) in FileWatch::targets(&path) {
+                // parents only report on the entries we care about
+                let mask = match name {
+                    Some(_) => {
+                        WatchMask::CREATE
+                            | WatchMask::CLOSE_WRITE
+                            | WatchMask::DELETE
+                            | WatchMask::MOVE
+                    }
+                    None => {
+                        WatchMask::CLOSE_WRITE
+                            | WatchMask::DELETE
+                            | WatchMask::MOVE
+                            | WatchMask::MOVE_SELF
+                            | WatchMask::DELETE_SELF
+                    }
+                };
+                // missing targets are picked up again through their parent
+                let Ok(wd) = self
+                    .inotify
+                    .watches()
+                    .add(&target, mask | WatchMask::MASK_ADD)
+                else {
+                    continue;
+                };
+                let entry = self.targets.entry(wd).or_insert(Target {
+                    path: target,
+                    names: Some(Vec::new()),
+                });
+                match (&mut entry.names, name) {
+                    (Some(names), Some(name)) => names.push(name),
+                    (names, None) => *names = None,
+                    (None, Some(_)) => {}
+                }
+            }
+        }
     }
-    /// Attach and inotify instance to a specific path
-    fn inotify_watch_path(path: &PathBuf) -> Result<bool, String> {
-        let mut buffer = [0; 1024];
-        let mut inotify = Inotify::init().map_err(|_| "inotify: failed to initialize")?;
-        inotify
-            .watches()
-            .add(
-                path,
-                WatchMask::MODIFY
-                    | WatchMask::DELETE
-                    | WatchMask::CREATE
-                    | WatchMask::MOVE
-                    | WatchMask::MOVE_SELF
-                    | WatchMask::DELETE_SELF
-                    | WatchMask::DONT_FOLLOW,
-            )
-            .map_err(|_| "inotify: failed to add watch")?;
-
-        let events = inotify
-            .read_events_blocking(&mut buffer)
-            .expect("inotify: failed to read events");
-        inotify.close().map_err(|_| "inotify: failed to close")?;
-
-        if events.count() > 0 {
-            Ok(true)
-        } else {
-            Ok(false)
+    /// Return every path to watch for a path, following its chain of symlinks
+    fn targets(path: &Path) -> Vec<(PathBuf, Option<OsString>)> {
+        let mut targets: Vec<(PathBuf, Option<OsString>)> = Vec::new();
+        let mut current = path.to_owned();
+        for _ in 0..MAX_SYMLINKS {
+            // the parent sees the entry being replaced, retargeted or renamed
+            let parent = match current.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => parent.to_owned(),
+                Some(_) => PathBuf::from("."),
+                None => break,
+            };
+            if let Some(name) = current.file_name() {
+                targets.push((parent.to_owned(), Some(name.to_owned())));
+            }
+            match fs::read_link(&current) {
+                Ok(link) => current = parent.join(link),
+                Err(_) => break,
+            }
         }
+        // the final target sees its content change
+        targets.push((current, None));
+
+        targets
     }
 }
@@ -1,4 +1,5 @@
 use serde::Serialize;
+use smithay_client_toolkit::reexports::calloop_wayland_source::WaylandSource;
 use smithay_client_toolkit::reexports::client::{
     Connection, EventQueue, QueueHandle, globals::registry_queue_init, protocol::wl_output,
 };
@@ -10,7 +11,7 @@ use smithay_client_toolkit::{
 };
 use std::fmt;
 
-struct ListOutputs {
+pub struct ListOutputs {
     registry_state: RegistryState,
     output_state: OutputState,
     needs_recalc: bool,
@@ -62,7 +63,7 @@ impl ProvidesRegistryState for ListOutputs {
     }
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, PartialEq)]
 pub struct Monitor {
     pub name: String,
     pub width: u32,
@@ -83,54 +84,20 @@ impl fmt::Display for Monitor {
     }
 }
 
-pub struct Wayland {
-    lo: ListOutputs,
-    eq: EventQueue<ListOutputs>,
-}
-
-impl Wayland {
-    /// Connect and return a new wayland connection
-    pub fn connect() -> Result<Self, String> {
-        // Try to connect to the Wayland server.
-        let conn = Connection::connect_to_env().map_err(|_| "wayland: failed to connect")?;
-
-        // Now create an event queue and a handle to the queue so we can create objects.
-        let (globals, event_queue) =
-            registry_queue_init(&conn).map_err(|_| "wayland: failed to init queue")?;
-        let qh = event_queue.handle();
-
-        // Initialize the registry handling
-        let registry_state = RegistryState::new(&globals);
-
-        // Initialize the delegate we will use for outputs.
-        let output_delegate = OutputState::new(&globals, &qh);
-
-        // Set up application state.
-        let list_outputs = ListOutputs {
-            registry_state,
-            output_state: output_delegate,
-            needs_recalc: false,
-        };
-
-        Ok(Self {
-            lo: list_outputs,
-            eq: event_queue,
-        })
+impl ListOutputs {
+    /// Return whether outputs changed since the last call
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.needs_recalc, false)
     }
-    /// Fetch and return the monitors in the current environment
-    pub fn get_monitors(&mut self) -> Result<Vec<Monitor>, String> {
-        // Initialize data
-        self.eq
-            .roundtrip(&mut self.lo)
-            .map_err(|_| "wayland: roundtrip failed")?;
-
-        // Now our outputs have been initialized with data,
+    /// Return the monitors as currently known
+    pub fn monitors(&self) -> Result<Vec<Monitor>, String> {
+        // our outputs have been initialized with data,
         // we may access what outputs exist and information about
         // said outputs using the output delegate.
-        let mut result: Vec<Monitor> = Vec::with_capacity(self.lo.output_state.outputs().count());
-        for output in self.lo.output_state.outputs() {
+        let mut result: Vec<Monitor> = Vec::with_capacity(self.output_state.outputs().count());
+        for output in self.output_state.outputs() {
             // get info
-            match self.lo.output_state.info(&output) {
+            match self.output_state.info(&output) {
                 Some(monitor_info) => {
                     // check for things we need and push
                     result.push(Monitor {
@@ -173,20 +140,62 @@ impl Wayland {
 
         Ok(result)
     }
-    /// Refresh and return recalculation boolean of the current wayland connection
-    pub fn refresh(&mut self) -> Result<bool, String> {
-        // roundtrip
+}
+
+pub struct Wayland {
+    conn: Connection,
+    lo: ListOutputs,
+    eq: EventQueue<ListOutputs>,
+}
+
+impl Wayland {
+    /// Connect and return a new wayland connection
+    pub fn connect() -> Result<Self, String> {
+        // Try to connect to the Wayland server.
+        let conn = Connection::connect_to_env().map_err(|_| "wayland: failed to connect")?;
+
+        // Now create an event queue and a handle to the queue so we can create objects.
+        let (globals, event_queue) =
+            registry_queue_init(&conn).map_err(|_| "wayland: failed to init queue")?;
+        let qh = event_queue.handle();
+
+        // Initialize the registry handling
+        let registry_state = RegistryState::new(&globals);
+
+        // Initialize the delegate we will use for outputs.
+        let output_delegate = OutputState::new(&globals, &qh);
+
+        // Set up application state.
+        let list_outputs = ListOutputs {
+            registry_state,
+            output_state: output_delegate,
+            needs_recalc: false,
+        };
+
+        Ok(Self {
+            conn,
+            lo: list_outputs,
+            eq: event_queue,
+        })
+    }
+    /// Fetch and return the monitors in the current environment
+    pub fn get_monitors(&mut self) -> Result<Vec<Monitor>, String> {
+        // Initialize data
         self.eq
             .roundtrip(&mut self.lo)
             .map_err(|_| "wayland: roundtrip failed")?;
-        // flush all in queue
-        self.eq.flush().map_err(|_| "wayland: flush failed")?;
-        // then wait for event
+
+        self.lo.monitors()
+    }
+    /// Hand the connection over to an event loop, keeping the output state
+    pub fn into_source(mut self) -> Result<(ListOutputs, WaylandSource<ListOutputs>), String> {
+        // Initialize data
         self.eq
-            .blocking_dispatch(&mut self.lo)
-            .map_err(|_| "wayland: event dispatch failed")?;
+            .roundtrip(&mut self.lo)
+            .map_err(|_| "wayland: roundtrip failed")?;
+        self.lo.needs_recalc = false;
 
-        Ok(self.lo.needs_recalc)
+        Ok((self.lo, WaylandSource::new(self.conn, self.eq)))
     }
 }
 
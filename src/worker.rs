@@ -1,28 +1,97 @@
-use crate::cli::{Alignment, Backend, Config, Locker};
+use crate::cli::{Alignment, Backend, Config, HookFailure, Slot};
 use crate::helpers::Helpers;
-use crate::integrations::{
-    hyprlock::Hyprlock, hyprpaper::Hyprpaper, palette::Palette, swaybg::Swaybg, swaylock::Swaylock,
-    wpaperd::Wpaperd,
-};
+use crate::integrations::{self, Registry, palette::Palette};
+use crate::ipc::Ipc;
 use crate::layout::{Layout, LayoutMonitor};
+use crate::playlist::{FitMode, Playlist, PlaylistItem};
+use crate::schedule::Schedule;
 use crate::wayland::Monitor;
+use ::serde::Serialize;
 use bincode::{config, serde};
 use glob::glob;
-use image::{DynamicImage, GenericImageView, imageops::FilterType};
+use image::{DynamicImage, GenericImageView, imageops, imageops::FilterType};
 use rand::seq::IndexedRandom;
 use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use std::cmp;
 use std::collections::HashMap;
 use std::env;
 use std::fs;
+use std::os::fd::OwnedFd;
 use std::os::unix;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const HISTORY_SIZE: usize = 32;
+
+// waybar reads text, tooltip and class, the rest is for scripts
+#[derive(Serialize)]
+pub struct Status {
+    pub text: String,
+    pub tooltip: String,
+    pub class: String,
+    pub image: Option<PathBuf>,
+    pub slot: Option<Slot>,
+    pub outputs: HashMap<String, String>,
+    pub palette: Option<PathBuf>,
+    pub paused: bool,
+    pub next_rotation: Option<u64>,
+}
+
+// what caused a run, passed on to hooks
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    Startup,
+    Hotplug,
+    File,
+    Timer,
+    Ipc,
+    Signal,
+}
+
+impl std::fmt::Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Startup => write!(f, "startup"),
+            Self::Hotplug => write!(f, "hotplug"),
+            Self::File => write!(f, "file"),
+            Self::Timer => write!(f, "timer"),
+            Self::Ipc => write!(f, "ipc"),
+            Self::Signal => write!(f, "signal"),
+        }
+    }
+}
+
+// everything a hook may want to know, unknown fields are empty before splitting
+#[derive(Serialize)]
+struct HookContext<'a> {
+    trigger: Trigger,
+    image: Option<&'a PathBuf>,
+    outputs: HashMap<String, String>,
+    palette: Option<&'a PathBuf>,
+    locker_config: Option<PathBuf>,
+    hash: Option<&'a String>,
+    monitors: &'a [Monitor],
+}
 
 pub struct Worker {
     hash: String,
     workdir: String,
     output: HashMap<String, String>,
+    playlist_position: usize,
+    rotate_at: Option<Instant>,
+    paused: bool,
+    remaining: Option<Duration>,
+    step: isize,
+    current: Option<PathBuf>,
+    history: Vec<PathBuf>,
+    input_override: Option<PathBuf>,
+    slot: Option<Slot>,
+    monitors: Vec<Monitor>,
+    index: Option<(PathBuf, Vec<PathBuf>)>,
+    palette: Option<PathBuf>,
+    backend: Option<(Backend, Box<dyn integrations::Backend>)>,
 }
 
 impl Worker {
@@ -31,30 +100,297 @@ impl Worker {
             hash: String::new(),
             workdir: String::new(),
             output: HashMap::new(),
+            playlist_position: 0,
+            rotate_at: None,
+            paused: false,
+            remaining: None,
+            step: 0,
+            current: None,
+            history: Vec::new(),
+            input_override: None,
+            slot: None,
+            monitors: Vec::new(),
+            index: None,
+            palette: None,
+            backend: None,
+        }
+    }
+    /// Check if the last run was applied to exactly these monitors
+    pub fn is_applied(&self, monitors: &[Monitor]) -> bool {
+        self.monitors == monitors
+    }
+    /// Return when the current playlist item should be rotated
+    pub fn rotate_at(&self) -> Option<Instant> {
+        if self.paused { None } else { self.rotate_at }
+    }
+    /// Move forward or backward by a number of images on the next run
+    pub fn advance(&mut self, step: isize) {
+        self.step += step;
+        self.rotate_at = None;
+        self.remaining = None;
+    }
+    /// Use another input than the configured one on the next run
+    pub fn set_input(&mut self, input_path: Option<PathBuf>) {
+        self.input_override = input_path;
+        self.playlist_position = 0;
+        self.step = 0;
+        self.current = None;
+        self.rotate_at = None;
+        self.remaining = None;
+    }
+    /// Show a specific image of the current directory on the next run
+    pub fn show(&mut self, image: PathBuf) {
+        if let Some(current) = self.current.replace(image) {
+            self.push_history(current);
+        }
+        self.step = 0;
+    }
+    /// Add a new image to the index of its directory
+    pub fn image_added(&mut self, image: &Path) {
+        if let Some((path, images)) = &mut self.index {
+            if image.parent() == Some(path.as_path()) && !images.iter().any(|known| known == image)
+            {
+                images.push(image.to_owned());
+            }
+        }
+    }
+    /// Remove an image from the index of its directory
+    pub fn image_removed(&mut self, image: &Path) {
+        if let Some((_, images)) = &mut self.index {
+            images.retain(|known| known != image);
+        }
+    }
+    /// Handle pending work of the backend between runs
+    pub fn dispatch(&mut self) -> Result<(), String> {
+        let Some((_, backend)) = &mut self.backend else {
+            return Ok(());
+        };
+        backend.dispatch()
+    }
+    /// Return the id of the connection the backend waits on for events
+    pub fn connection(&self) -> Option<u64> {
+        self.backend
+            .as_ref()
+            .and_then(|(_, backend)| backend.connection())
+    }
+    /// Return a copy of the connection the backend waits on for events
+    pub fn poll_fd(&self) -> Option<OwnedFd> {
+        self.backend
+            .as_ref()
+            .and_then(|(_, backend)| backend.poll_fd())
+    }
+    /// Reap processes of the backend, returning if any exited on their own
+    pub fn reap(&mut self) -> bool {
+        self.backend
+            .as_mut()
+            .is_some_and(|(_, backend)| backend.reap())
+    }
+    /// Apply the last run to the backend again, after it crashed
+    pub fn restart(&mut self, config: &Config) -> Result<(), String> {
+        let Some((_, backend)) = &mut self.backend else {
+            return Ok(());
+        };
+        backend.apply(config, &self.output, &self.hash)
+    }
+    /// Return the name of the current backend
+    pub fn backend(&self) -> Option<String> {
+        self.backend
+            .as_ref()
+            .map(|(backend, _)| backend.to_string())
+    }
+    /// Return the image shown by the last run
+    pub fn current(&self) -> Option<&Path> {
+        self.current.as_deref()
+    }
+    /// Check if an image is the one currently shown
+    pub fn is_current(&self, image: &Path) -> bool {
+        self.current.as_deref() == Some(image)
+    }
+    /// Pause the rotation and remember the time left
+    pub fn pause(&mut self) {
+        if !self.paused {
+            self.paused = true;
+            // the time left stays frozen until resumed
+            self.remaining = self
+                .rotate_at
+                .take()
+                .map(|instant| instant.saturating_duration_since(Instant::now()));
+        }
+    }
+    /// Resume the rotation with the time left
+    pub fn resume(&mut self) {
+        if self.paused {
+            self.paused = false;
+            if let Some(remaining) = self.remaining.take() {
+                self.rotate_at = Some(Instant::now() + remaining);
+            }
+        }
+    }
+    /// Return the current state of the worker
+    pub fn status(&self) -> Status {
+        let next_rotation = self
+            .rotate_at
+            .map(|instant| instant.saturating_duration_since(Instant::now()).as_secs())
+            .or(self.remaining.map(|remaining| remaining.as_secs()));
+
+        // short summary for status bars
+        let text = match &self.current {
+            Some(image) => image
+                .file_name()
+                .unwrap_or(image.as_os_str())
+                .to_string_lossy()
+                .to_string(),
+            None => String::new(),
+        };
+        let mut tooltip: Vec<String> = Vec::new();
+        if let Some(image) = &self.current {
+            tooltip.push(image.display().to_string());
+        }
+        if let Some(slot) = self.slot {
+            tooltip.push(format!("{} slot", slot));
+        }
+        match (self.paused, next_rotation) {
+            (true, _) => tooltip.push("paused".to_string()),
+            (false, Some(seconds)) => tooltip.push(format!("next in {}s", seconds)),
+            (false, None) => {}
+        }
+
+        Status {
+            text,
+            tooltip: tooltip.join("\n"),
+            class: if self.paused { "paused" } else { "active" }.to_string(),
+            image: self.current.to_owned(),
+            slot: self.slot,
+            outputs: self.output.to_owned(),
+            palette: self.palette.to_owned(),
+            paused: self.paused,
+            next_rotation,
         }
     }
     /// Initialize and run a new Worker instance
-    pub fn run(&mut self, config: &Config, monitors: Vec<Monitor>) -> Result<(), String> {
+    pub fn run(
+        &mut self,
+        config: &Config,
+        monitors: Vec<Monitor>,
+        trigger: Trigger,
+    ) -> Result<(), String> {
+        // a failed run keeps the selection, so a retry makes the same move again
+        let selection = (
+            self.current.clone(),
+            self.step,
+            self.history.clone(),
+            self.playlist_position,
+            self.rotate_at,
+            self.remaining,
+        );
+        if let Err(err) = self.apply(config, &monitors, trigger) {
+            (
+                self.current,
+                self.step,
+                self.history,
+                self.playlist_position,
+                self.rotate_at,
+                self.remaining,
+            ) = selection;
+            return Err(err);
+        }
+
+        // post run script check
+        if let Some(post_command) = &config.post_command {
+            let context = HookContext {
+                trigger,
+                image: self.current.as_ref(),
+                outputs: self.output.to_owned(),
+                palette: self.palette.as_ref(),
+                locker_config: config
+                    .locker
+                    .as_ref()
+                    .map(|locker| Registry::locker(locker).path(&self.workdir)),
+                hash: Some(&self.hash),
+                monitors: &monitors,
+            };
+            Worker::run_hook("post", post_command, &context, config)?;
+        }
+
+        // remember the layout this run was applied to
+        self.monitors = monitors;
+
+        Ok(())
+    }
+    /// Select the next image, then split and show it
+    fn apply(
+        &mut self,
+        config: &Config,
+        monitors: &Vec<Monitor>,
+        trigger: Trigger,
+    ) -> Result<(), String> {
         // pre run script check
-        if let Some(pre_script_path) = &config.pre_path {
-            Helpers::run_oneshot(pre_script_path)?;
+        if let Some(pre_command) = &config.pre_command {
+            let context = HookContext {
+                trigger,
+                image: None,
+                outputs: HashMap::new(),
+                palette: None,
+                locker_config: None,
+                hash: None,
+                monitors,
+            };
+            Worker::run_hook("pre", pre_command, &context, config)?;
+        }
+
+        // check for an overridden input or active schedule slot
+        self.slot = None;
+        let input_path = match (&self.input_override, Schedule::new(config)) {
+            (Some(input_path), _) => input_path.to_owned(),
+            (None, Some(schedule)) => {
+                let (slot, entry) = schedule.active();
+                self.slot = Some(slot);
+                entry.input_path.to_owned()
+            }
+            (None, None) => config.input_path.to_owned(),
+        };
+
+        // only watched directories keep their index up to date
+        if !config.watch || self.input_override.is_some() {
+            self.index = None;
         }
 
         // check input image type
         let target_image: PathBuf;
-        if fs::metadata(&config.input_path)
+        let mut playlist_item: Option<PlaylistItem> = None;
+        if config.input_bytes.is_some() && self.input_override.is_none() {
+            // image data is already in memory
+            target_image = input_path.to_owned();
+        } else if fs::metadata(&input_path)
             .map_err(|err| err.to_string())?
             .is_dir()
         {
             // image is random from directory
-            target_image = self.select_random_image(&config.input_path)?;
+            target_image = self.select_directory_image(&input_path)?;
+        } else if Config::is_playlist(&input_path) {
+            // image is the current playlist item
+            let item = self.select_playlist_item(&input_path)?;
+            target_image = item.path.to_owned();
+            playlist_item = Some(item);
         } else {
             // image is actual input
-            target_image = config.input_path.to_owned();
+            target_image = input_path.to_owned();
+        }
+
+        // only playlists rotate
+        if playlist_item.is_none() {
+            self.rotate_at = None;
+            self.remaining = None;
         }
+        self.step = 0;
+        self.current = Some(target_image.to_owned());
 
         // open original input image
-        let img = image::open(&target_image).map_err(|_| "failed to open image")?;
+        let img = match &config.input_bytes {
+            Some(bytes) if self.input_override.is_none() => image::load_from_memory(bytes),
+            _ => image::open(&target_image),
+        }
+        .map_err(|_| "failed to open image")?;
 
         // set workdir location
         if let Some(output_path) = &config.output_path {
@@ -80,28 +416,32 @@ impl Worker {
         }
 
         // ppi compensate if set
-        if config.ppi {
-            if !monitors
+        if config.ppi
+            && !monitors
                 .iter()
                 .all(|a| config.diagonals.contains_key(&a.name))
-            {
-                return Err("missing monitor definitions!".to_string());
-            };
-        }
+        {
+            return Err("missing monitor definitions!".to_string());
+        };
 
         // calculate hash
         self.hash = self.calculate_blake3_hash(vec![
-            serde::encode_to_vec(&config, config::standard())
+            serde::encode_to_vec(config, config::standard())
+                .map_err(|_| "serialization error".to_string())?
+                .as_slice(),
+            serde::encode_to_vec(monitors, config::standard())
                 .map_err(|_| "serialization error".to_string())?
                 .as_slice(),
-            serde::encode_to_vec(&monitors, config::standard())
+            target_image.as_os_str().as_encoded_bytes(),
+            serde::encode_to_vec(&playlist_item, config::standard())
                 .map_err(|_| "serialization error".to_string())?
                 .as_slice(),
+            config.input_bytes.as_deref().unwrap_or_default(),
         ]);
 
         // check caches first
         let caches_present: bool = self
-            .check_caches(&config, &monitors)
+            .check_caches(config, monitors)
             .map_err(|err| err.to_string())?;
 
         // do we need to resplit
@@ -110,107 +450,148 @@ impl Worker {
             self.cleanup_cache()?;
 
             // we need to resplit
-            let raw = self.perform_split(&monitors, img, config)?;
+            let raw = self.perform_split(monitors, img, config, playlist_item.as_ref())?;
 
             // save to path
             self.output = self.export_images(config, raw, &self.workdir)?;
+        } else {
+            // assemble the paths of the cached images
+            self.output = monitors
+                .iter()
+                .map(|monitor| {
+                    (
+                        monitor.name.to_owned(),
+                        format!("{}/rwps_{}_{}.png", &self.workdir, monitor.name, &self.hash),
+                    )
+                })
+                .collect();
+        }
+
+        // replace the backend instance once another one is configured
+        if self.backend.as_ref().map(|(backend, _)| backend) != config.backend.as_ref() {
+            if let Some((_, mut backend)) = self.backend.take() {
+                backend.shutdown();
+            }
+            self.backend = config
+                .backend
+                .as_ref()
+                .map(|backend| (backend.to_owned(), Registry::backend(backend)));
         }
 
         // check if we need to handle a backend
-        if let Some(backend) = &config.backend {
-            // recheck what integration we're working with
-            match backend {
-                Backend::Wpaperd => {
-                    // set and ensure config location
-                    let config_path = format!(
-                        "{}/.config/wpaperd/config.toml",
-                        env::var("HOME").map_err(|_| "failed read $HOME")?
-                    );
-                    self.ensure_path(&config_path)?;
-
-                    // check wpaper config hash
-                    let is_cached = Wpaperd::check_existing(&config_path, &self.hash);
-
-                    // do we need to rebuild config
-                    // also always rebuild when force resplit was set
-                    if config.force_resplit || !is_cached {
-                        // yes we do
-                        Wpaperd::new(&config_path, &self.hash, &self.output)?;
-                        // restart
-                        Helpers::force_restart("wpaperd", vec![])?;
-                    } else {
-                        // only start if we're not running already
-                        Helpers::soft_restart("wpaperd", vec![])?;
-                    }
-                }
-                Backend::Swaybg => {
-                    // start or restart the swaybg instance
-                    // considering present caches
-                    if config.force_resplit || !caches_present {
-                        let swaybg_args = Swaybg::new(&self.output)?;
-                        Helpers::force_restart("swaybg", swaybg_args)?;
-                    } else {
-                        // since swaybg has no config file, we need to assemble the names manually
-                        for mon in monitors {
-                            self.output.insert(
-                                mon.name.to_owned(),
-                                format!("{}/rwps_{}_{}.png", &self.workdir, &self.hash, mon.name),
-                            );
-                        }
-                        let swaybg_args = Swaybg::new(&self.output)?;
-                        Helpers::soft_restart("swaybg", swaybg_args)?;
-                    }
-                }
-                Backend::Hyprpaper => {
-                    // first soft restart
-                    Helpers::soft_restart("hyprpaper", vec![])?;
-                    if config.force_resplit || !caches_present {
-                        Hyprpaper::push(&self.output)?;
-                    } else {
-                        // hyprpaper also loads dynamically, so we need to manually assemble
-                        for monitor in monitors {
-                            self.output.insert(
-                                monitor.name.to_owned(),
-                                format!(
-                                    "{}/rwps_{}_{}.png",
-                                    &self.workdir, &self.hash, monitor.name
-                                ),
-                            );
-                        }
-                        Hyprpaper::push(&self.output)?;
-                    }
-                }
+        if let Some((_, backend)) = &mut self.backend {
+            if config.force_resplit || !caches_present || !backend.is_current(config, &self.hash)? {
+                backend.apply(config, &self.output, &self.hash)?;
             }
         }
 
         // check if we need to generate a locker config
         if let Some(locker) = &config.locker {
-            match locker {
-                Locker::Hyprlock => {
-                    if !caches_present || config.force_resplit {
-                        Hyprlock::new(&self.workdir, &self.output)?;
-                    }
-                }
-                Locker::Swaylock => {
-                    if !caches_present || config.force_resplit {
-                        Swaylock::new(&self.workdir, &self.output)?;
-                    }
-                }
+            if !caches_present || config.force_resplit {
+                Registry::locker(locker).generate(&self.workdir, &self.output)?;
             }
         }
 
         // check for palette bool
         if config.palette && !caches_present || config.force_resplit {
-            let color_palette = Palette::new(&target_image)?;
+            let color_palette = match &config.input_bytes {
+                Some(bytes) if self.input_override.is_none() => {
+                    Palette::from_memory(bytes, &target_image)?
+                }
+                _ => Palette::new(&target_image)?,
+            };
             color_palette.generate(&self.workdir)?;
         }
+        self.palette = config
+            .palette
+            .then(|| PathBuf::from(format!("{}/rwps_colors.json", &self.workdir)));
 
-        // post run script check
-        if let Some(post_script_path) = &config.post_path {
-            Helpers::run_oneshot(post_script_path)?;
+        Ok(())
+    }
+    /// Run a hook with the context of the current run in its environment
+    fn run_hook(
+        name: &str,
+        command: &[String],
+        context: &HookContext,
+        config: &Config,
+    ) -> Result<(), String> {
+        let mut envs: Vec<(String, String)> =
+            vec![("RWPS_TRIGGER".to_string(), context.trigger.to_string())];
+        if let Some(image) = context.image {
+            envs.push(("RWPS_IMAGE".to_string(), image.display().to_string()));
         }
 
-        Ok(())
+        // one variable per output, names are made safe for the shell
+        let mut names: Vec<&String> = context.outputs.keys().collect();
+        names.sort();
+        for name in &names {
+            envs.push((
+                format!(
+                    "RWPS_OUTPUT_{}",
+                    name.to_uppercase()
+                        .replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+                ),
+                context.outputs[*name].to_owned(),
+            ));
+        }
+        if !names.is_empty() {
+            envs.push((
+                "RWPS_OUTPUTS".to_string(),
+                names
+                    .iter()
+                    .map(|name| name.as_str())
+                    .collect::<Vec<&str>>()
+                    .join(" "),
+            ));
+        }
+        if let Some(palette) = context.palette {
+            envs.push(("RWPS_PALETTE".to_string(), palette.display().to_string()));
+        }
+        if let Some(locker_config) = &context.locker_config {
+            envs.push((
+                "RWPS_LOCKER_CONFIG".to_string(),
+                locker_config.display().to_string(),
+            ));
+        }
+        if let Some(hash) = context.hash {
+            envs.push(("RWPS_HASH".to_string(), hash.to_owned()));
+        }
+        envs.push((
+            "RWPS_MONITORS".to_string(),
+            context
+                .monitors
+                .iter()
+                .map(|mon| {
+                    format!(
+                        "{}:{}x{}+{}+{}",
+                        mon.name, mon.width, mon.height, mon.x, mon.y
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+        ));
+
+        // the full context for anything more involved
+        if let Ok(context_path) = Ipc::runtime_path("hook.json") {
+            let json = serde_json::to_string_pretty(context)
+                .map_err(|_| "serialization error".to_string())?;
+            fs::write(&context_path, json)
+                .map_err(|_| format!("failed to write {}", context_path.display()))?;
+            envs.push((
+                "RWPS_CONTEXT".to_string(),
+                context_path.display().to_string(),
+            ));
+        }
+
+        let timeout = config.hook_timeout.map(Duration::from_secs);
+        match Helpers::run_hook(name, command, &envs, timeout) {
+            Err(err) if config.hook_failure == HookFailure::Abort => Err(err),
+            Err(err) if config.hook_failure == HookFailure::Warn => {
+                eprintln!("rwpspread: \x1B[91m{}\x1B[39m", err);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
     }
     /// Perform the main splitting logic and return the resulting split images
     fn perform_split(
@@ -218,6 +599,7 @@ impl Worker {
         monitors: &[Monitor],
         mut input_image: DynamicImage,
         config: &Config,
+        playlist_item: Option<&PlaylistItem>,
     ) -> Result<Arc<Mutex<HashMap<String, DynamicImage>>>, String> {
         let mut layout = Layout::from_monitors(monitors);
 
@@ -228,9 +610,7 @@ impl Worker {
             // notify the user when ppi compensation might be worth a try
             if layout.ppi_advice {
                 println!(
-                    "{}: \x1B[93m{}\x1B[39m",
-                    "rwpspread",
-                    "detected monitors with different resolutions, try `--ppi` compensation!"
+                    "rwpspread: \x1B[93mdetected monitors with different resolutions, try `--ppi` compensation!\x1B[39m"
                 );
             }
         }
@@ -253,18 +633,26 @@ impl Worker {
             max_y = cmp::max(monitor.y1 + monitor.height as i32, max_y);
         }
 
+        // playlist items may override placement
+        let align = playlist_item
+            .and_then(|item| item.align.clone())
+            .or(config.align.clone());
+        let fit = playlist_item
+            .and_then(|item| item.fit)
+            .unwrap_or(FitMode::Fill);
+        let focus = playlist_item.and_then(|item| item.focus);
+
         // check if we can align the layout to a bigger input image
         let (mut resize_offset_x, mut resize_offset_y) = (0, 0);
-        if config.align.is_none()
+        if align.is_none()
             || input_image.dimensions().0 < max_x as u32
             || input_image.dimensions().1 < max_y as u32
         {
             // scale image to fit calculated size
-            input_image =
-                input_image.resize_to_fill(max_x as u32, max_y as u32, FilterType::Lanczos3);
+            input_image = self.scale_image(input_image, max_x as u32, max_y as u32, fit, focus);
         } else {
             // we align the monitor layout since we have some room to work with
-            if let Some(alignment) = &config.align {
+            if let Some(alignment) = &align {
                 match alignment {
                     Alignment::Tl => {
                         resize_offset_x = 0;
@@ -338,6 +726,50 @@ impl Worker {
             Err("initial splitting error".to_string())
         }
     }
+    /// Scale an image to the given size respecting fit mode and focus point
+    fn scale_image(
+        &self,
+        image: DynamicImage,
+        width: u32,
+        height: u32,
+        fit: FitMode,
+        focus: Option<(f32, f32)>,
+    ) -> DynamicImage {
+        match (fit, focus) {
+            (FitMode::Fill, None) => image.resize_to_fill(width, height, FilterType::Lanczos3),
+            (FitMode::Fill, Some((focus_x, focus_y))) => {
+                // scale to cover, then crop around the focus point
+                let (image_width, image_height) = image.dimensions();
+                let ratio = f64::max(
+                    width as f64 / image_width as f64,
+                    height as f64 / image_height as f64,
+                );
+                let scaled_width = ((image_width as f64 * ratio).ceil() as u32).max(width);
+                let scaled_height = ((image_height as f64 * ratio).ceil() as u32).max(height);
+                let offset_x = ((scaled_width as f32 * focus_x) as i64 - width as i64 / 2)
+                    .clamp(0, (scaled_width - width) as i64) as u32;
+                let offset_y = ((scaled_height as f32 * focus_y) as i64 - height as i64 / 2)
+                    .clamp(0, (scaled_height - height) as i64)
+                    as u32;
+                image
+                    .resize_exact(scaled_width, scaled_height, FilterType::Lanczos3)
+                    .crop_imm(offset_x, offset_y, width, height)
+            }
+            (FitMode::Fit, _) => {
+                // scale to contain and center on a black canvas
+                let scaled = image.resize(width, height, FilterType::Lanczos3);
+                let mut canvas = DynamicImage::new_rgb8(width, height);
+                imageops::overlay(
+                    &mut canvas,
+                    &scaled,
+                    ((width - scaled.width()) / 2) as i64,
+                    ((height - scaled.height()) / 2) as i64,
+                );
+                canvas
+            }
+            (FitMode::Stretch, _) => image.resize_exact(width, height, FilterType::Lanczos3),
+        }
+    }
     /// Export and save the images on disk and return their paths
     fn export_images(
         &self,
@@ -372,25 +804,91 @@ impl Worker {
         }
         hasher.finalize().to_hex().as_str().to_owned()
     }
+    /// Select and return an image of a directory, keeping the current one unless moved
+    fn select_directory_image(&mut self, path: &Path) -> Result<PathBuf, String> {
+        if self.step < 0 {
+            // go back in history
+            for _ in self.step..0 {
+                if let Some(previous) = self.history.pop() {
+                    self.current = Some(previous);
+                }
+            }
+        } else if self.step > 0 {
+            // remember the current image and pick another one
+            if let Some(current) = self.current.take() {
+                self.push_history(current.to_owned());
+                return self.select_random_image(path, Some(&current));
+            }
+        }
+
+        match &self.current {
+            Some(current) if current.starts_with(path) && current.exists() => {
+                Ok(current.to_owned())
+            }
+            _ => self.select_random_image(path, None),
+        }
+    }
+    /// Remember a previously shown image
+    fn push_history(&mut self, image: PathBuf) {
+        self.history.push(image);
+        if self.history.len() > HISTORY_SIZE {
+            self.history.remove(0);
+        }
+    }
     /// Select and return a path to a random image in a folder
-    fn select_random_image(&self, path: &PathBuf) -> Result<PathBuf, String> {
-        // iterate over valid filetypes and push to vec
-        let mut paths: Vec<PathBuf> = Vec::new();
-        for ext in &["png", "jpg", "jpeg"] {
-            let pattern = format!("{}/*.{}", path.display(), ext);
-            for entry in glob(&pattern).expect("Failed to read glob pattern") {
-                if let Ok(path) = entry {
-                    paths.push(path);
+    fn select_random_image(
+        &mut self,
+        path: &Path,
+        exclude: Option<&PathBuf>,
+    ) -> Result<PathBuf, String> {
+        // scan the directory unless its images are already known
+        if self.index.as_ref().is_none_or(|index| index.0 != path) {
+            // iterate over valid filetypes and push to vec
+            let mut images: Vec<PathBuf> = Vec::new();
+            for ext in &["png", "jpg", "jpeg"] {
+                let pattern = format!("{}/*.{}", path.display(), ext);
+                for path in glob(&pattern)
+                    .expect("Failed to read glob pattern")
+                    .flatten()
+                {
+                    images.push(path);
                 }
             }
+            self.index = Some((path.to_owned(), images));
         }
+        let mut paths: Vec<&PathBuf> = self.index.iter().flat_map(|index| &index.1).collect();
+
+        // avoid showing the same image twice if possible
+        if paths.len() > 1 {
+            paths.retain(|path| Some(*path) != exclude);
+        }
+
         // check if empty, else return
         if let Some(path) = paths.choose(&mut rand::rng()) {
-            Ok(path.to_owned())
+            Ok(path.to_path_buf())
         } else {
             Err("Images directory empty".to_string())
         }
     }
+    /// Select and return the current item of a playlist
+    fn select_playlist_item(&mut self, path: &Path) -> Result<PlaylistItem, String> {
+        // reload every time since the playlist might have changed
+        let playlist = Playlist::load(path)?;
+        self.playlist_position = (self.playlist_position as isize + self.step)
+            .rem_euclid(playlist.items.len() as isize) as usize;
+        let item = playlist.items[self.playlist_position].to_owned();
+
+        // start counting down once an item is first shown
+        if self.rotate_at.is_none() && self.remaining.is_none() {
+            if self.paused {
+                self.remaining = item.duration;
+            } else {
+                self.rotate_at = item.duration.map(|duration| Instant::now() + duration);
+            }
+        }
+
+        Ok(item)
+    }
     /// Ensure a path on disk exists
     fn ensure_path(&self, path: &str) -> Result<(), String> {
         let wanted_path = PathBuf::from(path);
@@ -410,12 +908,11 @@ impl Worker {
     /// Cleanup all cached items
     fn cleanup_cache(&self) -> Result<(), String> {
         // wildcard search for our images and delete them
-        for entry in
-            glob(&format!("{}/rwps_*", &self.workdir)).map_err(|_| "failed to iterate directory")?
+        for path in glob(&format!("{}/rwps_*", &self.workdir))
+            .map_err(|_| "failed to iterate directory")?
+            .flatten()
         {
-            if let Ok(path) = entry {
-                fs::remove_file(path).map_err(|_| "failed to clear cache")?;
-            }
+            fs::remove_file(path).map_err(|_| "failed to clear cache")?;
         }
 
         Ok(())
@@ -440,7 +937,12 @@ impl Worker {
             ));
         }
         if let Some(locker) = &config.locker {
-            runtime_paths.push(format!("{}/rwps_{}.conf", &self.workdir, locker));
+            runtime_paths.push(
+                Registry::locker(locker)
+                    .path(&self.workdir)
+                    .display()
+                    .to_string(),
+            );
         }
         if config.palette {
             runtime_paths.push(format!("{}/rwps_colors.json", &self.workdir));